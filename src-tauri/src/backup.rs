@@ -0,0 +1,423 @@
+use crate::db::{
+    backup_to, is_readable, latest_schema_version, load_config, open_readable, run_migrations, BackupRetention, Database,
+    DEFAULT_PROFILE_ID,
+};
+use chrono::{Datelike, Local, NaiveDateTime};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use rusqlite::{Connection, OpenFlags};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tauri::Manager;
+
+const BACKUP_PREFIX: &str = "tuinapp_";
+const BACKUP_TIMESTAMP_FORMAT: &str = "%Y-%m-%d_%H-%M-%S";
+const COMPRESSED_SUFFIX: &str = ".gz";
+
+/// Backups of the active profile. The default profile keeps using the top-level folder.
+pub fn get_backup_dir(app: &tauri::AppHandle) -> PathBuf {
+    let app_dir = app
+        .path()
+        .app_data_dir()
+        .expect("Failed to get app data dir");
+    let backups_dir = app_dir.join("backups");
+    match load_config(app).active_profile.as_str() {
+        DEFAULT_PROFILE_ID => backups_dir,
+        profile_id => backups_dir.join(profile_id),
+    }
+}
+
+/// Parse the timestamp out of a backup file name like `tuinapp_2026-03-01_09-30-00.db`
+/// or its compressed form `tuinapp_2026-03-01_09-30-00.db.gz`.
+fn backup_timestamp(file_name: &str) -> Option<NaiveDateTime> {
+    let name = file_name.strip_suffix(COMPRESSED_SUFFIX).unwrap_or(file_name);
+    let stamp = name.strip_prefix(BACKUP_PREFIX)?.strip_suffix(".db")?;
+    NaiveDateTime::parse_from_str(stamp, BACKUP_TIMESTAMP_FORMAT).ok()
+}
+
+fn is_compressed(path: &Path) -> bool {
+    path.to_string_lossy().ends_with(COMPRESSED_SUFFIX)
+}
+
+/// Backups in `backup_dir`, newest first. Files not named like a backup are ignored.
+fn backup_files(backup_dir: &Path) -> Vec<(PathBuf, NaiveDateTime)> {
+    let mut backups: Vec<(PathBuf, NaiveDateTime)> = std::fs::read_dir(backup_dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| {
+                    let path = entry.path();
+                    let timestamp = backup_timestamp(path.file_name()?.to_str()?)?;
+                    Some((path, timestamp))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    backups.sort_by_key(|(_, timestamp)| std::cmp::Reverse(*timestamp));
+    backups
+}
+
+/// Pick which backups to keep: the newest `keep_last`, plus the newest backup of each of the
+/// most recent `keep_daily` days, `keep_weekly` weeks and `keep_monthly` months.
+fn backups_to_keep(timestamps: &[NaiveDateTime], retention: &BackupRetention) -> HashSet<usize> {
+    let mut keep: HashSet<usize> = (0..timestamps.len().min(retention.keep_last)).collect();
+
+    let mut thin = |limit: usize, bucket: &dyn Fn(&NaiveDateTime) -> (i32, u32)| {
+        let mut seen = HashSet::new();
+        for (index, timestamp) in timestamps.iter().enumerate() {
+            if seen.len() >= limit {
+                break;
+            }
+            // Timestamps are newest first, so the first one in a bucket is its newest
+            if seen.insert(bucket(timestamp)) {
+                keep.insert(index);
+            }
+        }
+    };
+
+    thin(retention.keep_daily, &|t| (t.year(), t.ordinal()));
+    thin(retention.keep_weekly, &|t| (t.iso_week().year(), t.iso_week().week()));
+    thin(retention.keep_monthly, &|t| (t.year(), t.month()));
+
+    keep
+}
+
+/// Delete backups that fall outside the retention policy. Returns the number removed.
+pub fn prune_backups(backup_dir: &Path, retention: &BackupRetention) -> usize {
+    let backups = backup_files(backup_dir);
+    let timestamps: Vec<NaiveDateTime> = backups.iter().map(|(_, t)| *t).collect();
+    let keep = backups_to_keep(&timestamps, retention);
+
+    let mut removed = 0;
+    for (index, (path, _)) in backups.iter().enumerate() {
+        if keep.contains(&index) {
+            continue;
+        }
+        match std::fs::remove_file(path) {
+            Ok(_) => removed += 1,
+            Err(e) => eprintln!("Failed to remove old backup {}: {}", path.display(), e),
+        }
+    }
+    removed
+}
+
+fn compress_file(source: &Path, destination: &Path) -> std::io::Result<()> {
+    let mut input = File::open(source)?;
+    let mut encoder = GzEncoder::new(File::create(destination)?, Compression::default());
+    std::io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?.sync_all()
+}
+
+/// Write a backup out as a plain database file at `destination`, decompressing it if needed.
+fn extract_backup(backup_path: &Path, destination: &Path) -> std::io::Result<()> {
+    if is_compressed(backup_path) {
+        let mut decoder = GzDecoder::new(File::open(backup_path)?);
+        let mut output = File::create(destination)?;
+        std::io::copy(&mut decoder, &mut output)?;
+        output.sync_all()
+    } else {
+        std::fs::copy(backup_path, destination).map(|_| ())
+    }
+}
+
+/// Snapshot the open database into `backup_dir` using SQLite's online backup API, which
+/// copies a consistent state even while other connections are writing. The snapshot is
+/// written under a temporary name first, so an interrupted backup never looks like a real one.
+/// Backups of an encrypted database are encrypted with the same passphrase.
+pub fn create_backup(conn: &Connection, backup_dir: &Path, compress: bool, key: Option<&str>) -> Result<PathBuf, String> {
+    std::fs::create_dir_all(backup_dir).map_err(|e| format!("Failed to create backup directory: {}", e))?;
+
+    let timestamp = Local::now().format(BACKUP_TIMESTAMP_FORMAT);
+    let backup_path = backup_dir.join(format!("{}{}.db", BACKUP_PREFIX, timestamp));
+    let partial_path = backup_path.with_extension("db.partial");
+
+    backup_to(conn, &partial_path, key).map_err(|e| format!("Failed to backup database: {}", e))?;
+
+    if !compress {
+        std::fs::rename(&partial_path, &backup_path).map_err(|e| format!("Failed to save backup: {}", e))?;
+        return Ok(backup_path);
+    }
+
+    let compressed_path = backup_dir.join(format!("{}{}.db{}", BACKUP_PREFIX, timestamp, COMPRESSED_SUFFIX));
+    let partial_compressed_path = compressed_path.with_extension("gz.partial");
+    let result = compress_file(&partial_path, &partial_compressed_path)
+        .and_then(|_| std::fs::rename(&partial_compressed_path, &compressed_path));
+    let _ = std::fs::remove_file(&partial_path);
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&partial_compressed_path);
+        return Err(format!("Failed to compress backup: {}", e));
+    }
+    Ok(compressed_path)
+}
+
+/// Back up the database with the configured settings, then prune old backups.
+pub fn backup_database(app: &tauri::AppHandle, conn: &Connection) -> Result<PathBuf, String> {
+    let config = load_config(app);
+    let profile = config.active();
+    let backup_dir = get_backup_dir(app);
+
+    let key = app.state::<Database>().get_key();
+    let backup_path = create_backup(conn, &backup_dir, profile.backup_schedule.compress, key.as_deref())?;
+    println!("Database backed up to: {}", backup_path.display());
+
+    let removed = prune_backups(&backup_dir, &profile.backup_retention);
+    if removed > 0 {
+        println!("Removed {} old backup(s)", removed);
+    }
+    Ok(backup_path)
+}
+
+pub fn rows_changed(conn: &Connection) -> u64 {
+    conn.query_row("SELECT total_changes()", [], |row| row.get::<_, i64>(0))
+        .map(|changes| changes as u64)
+        .unwrap_or(0)
+}
+
+/// Take backups while the app runs: every `interval_minutes` if anything changed since the
+/// last backup, or as soon as `after_changes` rows have changed.
+pub fn start_backup_scheduler(app: tauri::AppHandle) {
+    std::thread::spawn(move || {
+        let mut last_backup = Instant::now();
+        let mut changes_at_last_backup = 0;
+
+        loop {
+            std::thread::sleep(Duration::from_secs(60));
+
+            let schedule = load_config(&app).active().backup_schedule.clone();
+            let db = app.state::<Database>();
+            let Ok(conn) = db.conn.lock() else { continue };
+
+            let changes = rows_changed(&conn);
+            // The counter starts over when the connection is replaced, e.g. after a restore
+            if changes < changes_at_last_backup {
+                changes_at_last_backup = 0;
+            }
+            let pending = changes - changes_at_last_backup;
+
+            let interval = Duration::from_secs(u64::from(schedule.interval_minutes) * 60);
+            let interval_due = schedule.interval_minutes > 0 && pending > 0 && last_backup.elapsed() >= interval;
+            let changes_due = schedule.after_changes > 0 && pending >= u64::from(schedule.after_changes);
+            if !interval_due && !changes_due {
+                continue;
+            }
+
+            if let Err(e) = backup_database(&app, &conn) {
+                eprintln!("Scheduled backup failed: {}", e);
+            }
+            last_backup = Instant::now();
+            changes_at_last_backup = changes;
+        }
+    });
+}
+
+#[derive(Debug, Serialize)]
+pub struct BackupInfo {
+    pub file_name: String,
+    pub size_bytes: u64,
+    pub created_at: String,
+    pub compressed: bool,
+    /// None when the backup can't be opened as a tuinapp database
+    pub plant_count: Option<i64>,
+    pub schema_version: Option<i32>,
+}
+
+/// Open a backup read-only. Backups made before the database was encrypted, or after it was
+/// decrypted, are plain, so `key` is only used if the backup needs it.
+fn open_read_only(path: &Path, key: Option<&str>) -> rusqlite::Result<Connection> {
+    let open = |key: Option<&str>| -> rusqlite::Result<Connection> {
+        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX)?;
+        if let Some(key) = key {
+            conn.pragma_update(None, "key", key)?;
+        }
+        Ok(conn)
+    };
+    let conn = open(key)?;
+    if key.is_some() && !is_readable(&conn) {
+        return open(None);
+    }
+    Ok(conn)
+}
+
+fn read_backup_stats(path: &Path, key: Option<&str>) -> rusqlite::Result<(i64, i32)> {
+    let conn = open_read_only(path, key)?;
+    let plant_count = conn.query_row("SELECT COUNT(*) FROM plants", [], |row| row.get(0))?;
+    let schema_version = conn.query_row("SELECT version FROM schema_version LIMIT 1", [], |row| row.get(0))?;
+    Ok((plant_count, schema_version))
+}
+
+/// Stats for a backup, decompressing it to a temporary file first if needed.
+fn backup_stats(path: &Path, key: Option<&str>) -> Option<(i64, i32)> {
+    if !is_compressed(path) {
+        return read_backup_stats(path, key).ok();
+    }
+    let temp_path = std::env::temp_dir().join(path.file_name()?).with_extension("");
+    let stats = extract_backup(path, &temp_path).ok().and_then(|_| read_backup_stats(&temp_path, key).ok());
+    let _ = std::fs::remove_file(&temp_path);
+    stats
+}
+
+pub fn list_backups(backup_dir: &Path, key: Option<&str>) -> Vec<BackupInfo> {
+    backup_files(backup_dir)
+        .into_iter()
+        .map(|(path, timestamp)| {
+            let stats = backup_stats(&path, key);
+            BackupInfo {
+                file_name: path.file_name().unwrap().to_string_lossy().to_string(),
+                size_bytes: std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0),
+                created_at: timestamp.format("%Y-%m-%d %H:%M:%S").to_string(),
+                compressed: is_compressed(&path),
+                plant_count: stats.map(|(count, _)| count),
+                schema_version: stats.map(|(_, version)| version),
+            }
+        })
+        .collect()
+}
+
+/// Check that a file is a healthy tuinapp database this version of the app can open.
+fn validate_backup(path: &Path, key: Option<&str>) -> Result<(), String> {
+    let conn = open_read_only(path, key).map_err(|e| format!("Backup can't be opened: {}", e))?;
+
+    let integrity: String = conn
+        .query_row("PRAGMA integrity_check", [], |row| row.get(0))
+        .map_err(|e| format!("Backup is not a valid database: {}", e))?;
+    if integrity != "ok" {
+        return Err(format!("Backup failed the integrity check: {}", integrity));
+    }
+
+    let (_, version) =
+        read_backup_stats(path, key).map_err(|_| "Backup is not a tuinapp database, or is encrypted with another passphrase".to_string())?;
+    if version > latest_schema_version() {
+        return Err(format!("Backup is from a newer version of the app (schema version {})", version));
+    }
+    Ok(())
+}
+
+/// Replace the open database with a backup. The backup is extracted next to the database and
+/// validated there, the current database is backed up, and then the file is renamed over the
+/// database so the swap is atomic.
+pub fn restore_backup(app: &tauri::AppHandle, db: &Database, file_name: &str) -> Result<(), String> {
+    if backup_timestamp(file_name).is_none() || file_name.contains(['/', '\\']) {
+        return Err(format!("Not a backup: {}", file_name));
+    }
+    let backup_path = get_backup_dir(app).join(file_name);
+
+    let db_path = db.get_path();
+    let staging_path = db_path.with_extension("db.restoring");
+    extract_backup(&backup_path, &staging_path).map_err(|e| format!("Failed to copy backup: {}", e))?;
+    if let Err(e) = validate_backup(&staging_path, db.get_key().as_deref()) {
+        let _ = std::fs::remove_file(&staging_path);
+        return Err(e);
+    }
+
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    // Keep a copy of what we're about to overwrite, so a restore can itself be undone
+    if let Err(e) = backup_database(app, &conn) {
+        let _ = std::fs::remove_file(&staging_path);
+        return Err(e);
+    }
+
+    // Close the current connection before replacing the file underneath it
+    let placeholder = Connection::open_in_memory().map_err(|e| e.to_string())?;
+    drop(std::mem::replace(&mut *conn, placeholder));
+
+    let swap = std::fs::rename(&staging_path, &db_path).map_err(|e| format!("Failed to replace database: {}", e));
+
+    // Reopen whatever is at the database path now: the restored backup, or the original if the
+    // swap failed. The backup may be encrypted differently from the database it replaced.
+    let (mut reopened, key) = open_readable(&db_path, db.get_key().as_deref())?;
+    let migrated = if swap.is_ok() { run_migrations(&mut reopened) } else { Ok(()) };
+    *conn = reopened;
+    *db.key.lock().unwrap() = key;
+
+    let _ = std::fs::remove_file(&staging_path);
+    swap?;
+    migrated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(timestamp: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn retention(keep_last: usize, keep_daily: usize, keep_weekly: usize, keep_monthly: usize) -> BackupRetention {
+        BackupRetention { keep_last, keep_daily, keep_weekly, keep_monthly }
+    }
+
+    fn kept(timestamps: &[NaiveDateTime], retention: &BackupRetention) -> Vec<usize> {
+        let mut kept: Vec<usize> = backups_to_keep(timestamps, retention).into_iter().collect();
+        kept.sort();
+        kept
+    }
+
+    #[test]
+    fn parses_backup_file_names() {
+        let expected = Some(at("2026-03-01 09:30"));
+        assert_eq!(backup_timestamp("tuinapp_2026-03-01_09-30-00.db"), expected);
+        assert_eq!(backup_timestamp("tuinapp_2026-03-01_09-30-00.db.gz"), expected);
+        assert_eq!(backup_timestamp("tuinapp_2026-03-01_09-30-00.db.partial"), None);
+        assert_eq!(backup_timestamp("garden.db"), None);
+    }
+
+    #[test]
+    fn keeps_the_newest_backups() {
+        let timestamps: Vec<_> = (0..5).map(|day| at(&format!("2026-03-{:02} 12:00", 10 - day))).collect();
+        assert_eq!(kept(&timestamps, &retention(3, 0, 0, 0)), [0, 1, 2]);
+        assert_eq!(kept(&timestamps, &retention(10, 0, 0, 0)), [0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn keeps_the_newest_backup_of_each_day() {
+        let timestamps = [
+            at("2026-03-03 18:00"),
+            at("2026-03-03 09:00"),
+            at("2026-03-02 20:00"),
+            at("2026-03-02 08:00"),
+            at("2026-03-01 12:00"),
+        ];
+        assert_eq!(kept(&timestamps, &retention(0, 2, 0, 0)), [0, 2]);
+        assert_eq!(kept(&timestamps, &retention(1, 7, 0, 0)), [0, 2, 4]);
+    }
+
+    #[test]
+    fn keeps_the_newest_backup_of_each_week_and_month() {
+        let timestamps = [
+            at("2026-03-04 12:00"), // Wednesday, week 10
+            at("2026-03-02 12:00"), // Monday, week 10
+            at("2026-03-01 12:00"), // Sunday, week 9
+            at("2026-01-20 12:00"),
+            at("2025-12-31 12:00"),
+        ];
+        assert_eq!(kept(&timestamps, &retention(0, 0, 2, 0)), [0, 2]);
+        // Months without backups don't count towards the limit
+        assert_eq!(kept(&timestamps, &retention(0, 0, 0, 2)), [0, 3]);
+        assert_eq!(kept(&timestamps, &retention(0, 0, 0, 12)), [0, 3, 4]);
+    }
+
+    #[test]
+    fn compressed_backups_restore_the_database() {
+        let dir = std::env::temp_dir().join(format!("tuinapp-backup-test-{}", std::process::id()));
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE plants (name TEXT); INSERT INTO plants VALUES ('Tomato');").unwrap();
+
+        let backup = create_backup(&conn, &dir, true, None).unwrap();
+        assert!(is_compressed(&backup));
+        assert_eq!(backup_files(&dir).len(), 1);
+
+        let restored = dir.join("restored.db");
+        extract_backup(&backup, &restored).unwrap();
+        let name: String = Connection::open(&restored)
+            .unwrap()
+            .query_row("SELECT name FROM plants", [], |row| row.get(0))
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(name, "Tomato");
+    }
+}
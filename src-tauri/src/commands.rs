@@ -1,425 +1,2537 @@
-use crate::db::{save_config, AppConfig, Database};
-use crate::models::{Activity, Plant, PlantPhoto};
+use crate::backup::{backup_database, get_backup_dir, BackupInfo};
+use crate::db::{
+    check_integrity, current_schema_version, get_profile_db_path, latest_schema_version, load_config, run_migrations,
+    save_config, BackupRetention, BackupSchedule, Database, IntegrityReport, Profile, RelocateMode, StartupState,
+    DEFAULT_PROFILE_ID,
+};
+use crate::location::{shift_periods, shift_periods_sql, shift_plant, Location};
+use crate::merge::MergeReport;
+use crate::models::{Activity, JournalEntry, Plant, PlantCategory, PlantPhoto, PlantTask, Supplier, Tag};
+use crate::photos::{
+    backfill_photos, content_hash, exif_taken_at, is_near_duplicate, mime_type, original_path, process_photo,
+    remove_unused_originals, store_original, strip_metadata, PhotoOwner, PhotoSettings, ProcessedPhoto,
+};
+use crate::search::{fts_query, SearchResult};
+use crate::sync::{ExternalChangeResolution, SyncState, SyncStatus};
 use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{Datelike, Local};
+use printpdf::path::PaintMode;
 use printpdf::*;
+use rusqlite::{Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
 use std::io::BufWriter;
+use std::path::Path;
 use std::process::Command;
-use tauri::{AppHandle, State};
+use tauri::{AppHandle, Manager, State};
+
+const PLANT_COLUMNS: &str = "id, name, plant_type, sun_requirements, sow_periods, plant_periods, notes, needs_reorder, supplier_id, packets_needed, last_price, created_at, updated_at,
+    scientific_name, cultivar, family, lifecycle, hardiness_zone, sowing_depth_cm, in_row_spacing_cm, row_spacing_cm, mature_height_cm, mature_spread_cm, water_needs, parent_id,
+    bloom_periods, flower_color, established_year,
+    (SELECT json_group_array(tag_id) FROM plant_tags WHERE plant_id = plants.id), inherited_fields";
+
+/// Parse the JSON in column `index`, like the tag ids selected with `json_group_array`.
+fn json_from_row<T: serde::de::DeserializeOwned>(row: &rusqlite::Row, index: usize) -> rusqlite::Result<T> {
+    let json: String = row.get(index)?;
+    serde_json::from_str(&json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, Box::new(e)))
+}
+
+/// Give a plant or activity exactly these tags. Links that stay keep their UUIDs, so merges
+/// only see the tags that were added or removed.
+fn set_tags(conn: &Connection, link_table: &str, owner_column: &str, owner_id: i64, tag_ids: &[i64]) -> Result<(), String> {
+    let tag_ids = serde_json::to_string(tag_ids).map_err(|e| e.to_string())?;
+    conn.execute(
+        &format!(
+            "DELETE FROM {0} WHERE {1} = ?1 AND tag_id NOT IN (SELECT value FROM json_each(?2))",
+            link_table, owner_column
+        ),
+        rusqlite::params![owner_id, &tag_ids],
+    ).map_err(|e| e.to_string())?;
+    conn.execute(
+        &format!(
+            "INSERT OR IGNORE INTO {0} ({1}, tag_id) SELECT ?1, value FROM json_each(?2)",
+            link_table, owner_column
+        ),
+        rusqlite::params![owner_id, &tag_ids],
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn plant_from_row(row: &rusqlite::Row) -> rusqlite::Result<Plant> {
+    let needs_reorder: i32 = row.get(7)?;
+    Ok(Plant {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        plant_type: row.get(2)?,
+        sun_requirements: row.get(3)?,
+        sow_periods: row.get(4)?,
+        plant_periods: row.get(5)?,
+        notes: row.get(6)?,
+        needs_reorder: needs_reorder != 0,
+        supplier_id: row.get(8)?,
+        packets_needed: row.get::<_, Option<i32>>(9)?.unwrap_or(1),
+        last_price: row.get(10)?,
+        created_at: row.get(11)?,
+        updated_at: row.get(12)?,
+        scientific_name: row.get(13)?,
+        cultivar: row.get(14)?,
+        family: row.get(15)?,
+        lifecycle: row.get(16)?,
+        hardiness_zone: row.get(17)?,
+        sowing_depth_cm: row.get(18)?,
+        in_row_spacing_cm: row.get(19)?,
+        row_spacing_cm: row.get(20)?,
+        mature_height_cm: row.get(21)?,
+        mature_spread_cm: row.get(22)?,
+        water_needs: row.get(23)?,
+        parent_id: row.get(24)?,
+        bloom_periods: row.get(25)?,
+        flower_color: row.get(26)?,
+        established_year: row.get(27)?,
+        tag_ids: Some(json_from_row(row, 28)?),
+        inherited_fields: Some(json_from_row(row, 29)?),
+    })
+}
+
+/// Half-months plant periods move for the active garden's location. Plants are stored with the
+/// periods of the reference climate; commands shift them on the way out and back on the way in.
+fn period_shift(app: &AppHandle) -> Result<i32, String> {
+    load_config(app).active().location.period_shift()
+}
+
+fn shift_plants(plants: &mut [Plant], shift: i32) {
+    for plant in plants {
+        shift_plant(plant, shift);
+    }
+}
 
 #[tauri::command]
-pub fn get_all_plants(db: State<Database>) -> Result<Vec<Plant>, String> {
+pub fn get_all_plants(app: AppHandle, db: State<Database>) -> Result<Vec<Plant>, String> {
+    let shift = period_shift(&app)?;
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
 
     let mut stmt = conn
-        .prepare("SELECT id, name, plant_type, sun_requirements, sow_periods, plant_periods, notes, needs_reorder, created_at, updated_at FROM plants ORDER BY name")
+        .prepare(&format!("SELECT {} FROM plants ORDER BY name", PLANT_COLUMNS))
         .map_err(|e| e.to_string())?;
 
-    let plants = stmt
-        .query_map([], |row| {
-            let needs_reorder: i32 = row.get(7)?;
-            Ok(Plant {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                plant_type: row.get(2)?,
-                sun_requirements: row.get(3)?,
-                sow_periods: row.get(4)?,
-                plant_periods: row.get(5)?,
-                notes: row.get(6)?,
-                needs_reorder: needs_reorder != 0,
-                created_at: row.get(8)?,
-                updated_at: row.get(9)?,
-            })
-        })
+    let mut plants = stmt
+        .query_map([], plant_from_row)
         .map_err(|e| e.to_string())?
         .collect::<Result<Vec<_>, _>>()
         .map_err(|e| e.to_string())?;
+    shift_plants(&mut plants, shift);
 
     Ok(plants)
 }
 
 #[tauri::command]
-pub fn create_plant(db: State<Database>, plant: Plant) -> Result<Plant, String> {
+pub fn create_plant(app: AppHandle, db: State<Database>, mut plant: Plant) -> Result<Plant, String> {
+    let shift = period_shift(&app)?;
+    shift_plant(&mut plant, -shift);
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
 
+    if let Some(parent_id) = plant.parent_id {
+        check_species(&conn, None, parent_id)?;
+    }
+
     conn.execute(
-        "INSERT INTO plants (name, plant_type, sun_requirements, sow_periods, plant_periods, notes, needs_reorder) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-        (&plant.name, &plant.plant_type, &plant.sun_requirements, &plant.sow_periods, &plant.plant_periods, &plant.notes, &plant.needs_reorder),
+        "INSERT INTO plants (name, plant_type, sun_requirements, sow_periods, plant_periods, notes, needs_reorder, supplier_id, packets_needed, last_price,
+            scientific_name, cultivar, family, lifecycle, hardiness_zone, sowing_depth_cm, in_row_spacing_cm, row_spacing_cm, mature_height_cm, mature_spread_cm, water_needs, parent_id,
+            bloom_periods, flower_color, established_year)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25)",
+        rusqlite::params![&plant.name, &plant.plant_type, &plant.sun_requirements, &plant.sow_periods, &plant.plant_periods, &plant.notes, &plant.needs_reorder, &plant.supplier_id, &plant.packets_needed, &plant.last_price,
+            &plant.scientific_name, &plant.cultivar, &plant.family, &plant.lifecycle, &plant.hardiness_zone, &plant.sowing_depth_cm, &plant.in_row_spacing_cm, &plant.row_spacing_cm, &plant.mature_height_cm, &plant.mature_spread_cm, &plant.water_needs, &plant.parent_id,
+            &plant.bloom_periods, &plant.flower_color, &plant.established_year],
     ).map_err(|e| e.to_string())?;
 
     let id = conn.last_insert_rowid();
+    if let Some(tag_ids) = &plant.tag_ids {
+        set_tags(&conn, "plant_tags", "plant_id", id, tag_ids)?;
+    }
+    match &plant.inherited_fields {
+        Some(fields) => set_inherited_fields(&conn, id, fields)?,
+        None => inherit_unset_fields(&conn, id)?,
+    }
 
-    Ok(Plant {
-        id: Some(id),
-        ..plant
-    })
+    let mut plant = load_plant(&conn, id)?;
+    shift_plant(&mut plant, shift);
+    Ok(plant)
 }
 
 #[tauri::command]
-pub fn update_plant(db: State<Database>, plant: Plant) -> Result<(), String> {
+pub fn update_plant(app: AppHandle, db: State<Database>, mut plant: Plant) -> Result<(), String> {
+    shift_plant(&mut plant, -period_shift(&app)?);
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
 
+    if let Some(parent_id) = plant.parent_id {
+        check_species(&conn, plant.id, parent_id)?;
+    }
+    let previous_parent_id: Option<i64> = conn
+        .query_row("SELECT parent_id FROM plants WHERE id = ?1", [plant.id], |row| row.get(0))
+        .optional()
+        .map_err(|e| e.to_string())?
+        .flatten();
+
     conn.execute(
-        "UPDATE plants SET name = ?1, plant_type = ?2, sun_requirements = ?3, sow_periods = ?4, plant_periods = ?5, notes = ?6, needs_reorder = ?7, updated_at = CURRENT_TIMESTAMP WHERE id = ?8",
-        (&plant.name, &plant.plant_type, &plant.sun_requirements, &plant.sow_periods, &plant.plant_periods, &plant.notes, &plant.needs_reorder, &plant.id),
+        "UPDATE plants SET name = ?1, plant_type = ?2, sun_requirements = ?3, sow_periods = ?4, plant_periods = ?5, notes = ?6, needs_reorder = ?7, supplier_id = ?8, packets_needed = ?9, last_price = ?10,
+            scientific_name = ?11, cultivar = ?12, family = ?13, lifecycle = ?14, hardiness_zone = ?15, sowing_depth_cm = ?16, in_row_spacing_cm = ?17, row_spacing_cm = ?18, mature_height_cm = ?19, mature_spread_cm = ?20, water_needs = ?21,
+            parent_id = ?22, bloom_periods = ?23, flower_color = ?24, established_year = ?25, updated_at = CURRENT_TIMESTAMP WHERE id = ?26",
+        rusqlite::params![&plant.name, &plant.plant_type, &plant.sun_requirements, &plant.sow_periods, &plant.plant_periods, &plant.notes, &plant.needs_reorder, &plant.supplier_id, &plant.packets_needed, &plant.last_price,
+            &plant.scientific_name, &plant.cultivar, &plant.family, &plant.lifecycle, &plant.hardiness_zone, &plant.sowing_depth_cm, &plant.in_row_spacing_cm, &plant.row_spacing_cm, &plant.mature_height_cm, &plant.mature_spread_cm, &plant.water_needs, &plant.parent_id,
+            &plant.bloom_periods, &plant.flower_color, &plant.established_year, &plant.id],
     ).map_err(|e| e.to_string())?;
 
+    if let (Some(id), Some(tag_ids)) = (plant.id, &plant.tag_ids) {
+        set_tags(&conn, "plant_tags", "plant_id", id, tag_ids)?;
+    }
+    if let Some(id) = plant.id {
+        match &plant.inherited_fields {
+            Some(fields) => set_inherited_fields(&conn, id, fields)?,
+            // A plant that just became a variety takes what it leaves empty from its species
+            None if plant.parent_id.is_some() && plant.parent_id != previous_parent_id => inherit_unset_fields(&conn, id)?,
+            None if plant.parent_id.is_none() => set_inherited_fields(&conn, id, &[])?,
+            None => copy_inherited_fields(&conn, id)?,
+        }
+    }
+
     Ok(())
 }
 
-#[tauri::command]
-pub fn delete_plant(db: State<Database>, id: i64) -> Result<(), String> {
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+fn load_plant(conn: &Connection, id: i64) -> Result<Plant, String> {
+    conn.query_row(&format!("SELECT {} FROM plants WHERE id = ?1", PLANT_COLUMNS), [id], plant_from_row)
+        .map_err(|e| e.to_string())
+}
 
-    conn.execute("DELETE FROM plants WHERE id = ?1", [id])
+/// Values a variety can share with its species. Each variety lists the ones it inherits in
+/// `inherited_fields`; the `plants_varieties_inherit` trigger copies changes of the species to them.
+const INHERITED_FIELDS: &[&str] = &[
+    "plant_type",
+    "sun_requirements",
+    "sow_periods",
+    "plant_periods",
+    "scientific_name",
+    "family",
+    "lifecycle",
+    "hardiness_zone",
+    "sowing_depth_cm",
+    "in_row_spacing_cm",
+    "row_spacing_cm",
+    "mature_height_cm",
+    "mature_spread_cm",
+    "water_needs",
+    "bloom_periods",
+];
+
+/// Condition for a field that isn't filled in
+fn unset_condition(field: &str) -> String {
+    match field {
+        "sun_requirements" | "sow_periods" | "plant_periods" | "bloom_periods" => format!("{} = 0", field),
+        _ => format!("{} IS NULL", field),
+    }
+}
+
+/// Let a variety inherit what it leaves empty from its species.
+fn inherit_unset_fields(conn: &Connection, id: i64) -> Result<(), String> {
+    let conditions = INHERITED_FIELDS.iter().map(|field| unset_condition(field)).collect::<Vec<_>>().join(", ");
+    let unset: Vec<bool> = conn
+        .query_row(
+            &format!("SELECT {} FROM plants WHERE id = ?1", conditions),
+            [id],
+            |row| (0..INHERITED_FIELDS.len()).map(|i| row.get(i)).collect(),
+        )
         .map_err(|e| e.to_string())?;
+    let fields = INHERITED_FIELDS
+        .iter()
+        .zip(unset)
+        .filter(|(_, unset)| *unset)
+        .map(|(field, _)| field.to_string())
+        .collect::<Vec<_>>();
+    set_inherited_fields(conn, id, &fields)
+}
+
+/// Make `fields` the ones a variety inherits and take their values from the species. A plant
+/// that isn't a variety inherits nothing.
+fn set_inherited_fields(conn: &Connection, id: i64, fields: &[String]) -> Result<(), String> {
+    if let Some(field) = fields.iter().find(|field| !INHERITED_FIELDS.contains(&field.as_str())) {
+        return Err(format!("{} can't be inherited from the species", field));
+    }
+    let fields = serde_json::to_string(fields).map_err(|e| e.to_string())?;
+    conn.execute(
+        "UPDATE plants SET inherited_fields = CASE WHEN parent_id IS NULL THEN '[]' ELSE ?2 END WHERE id = ?1",
+        rusqlite::params![id, &fields],
+    ).map_err(|e| e.to_string())?;
+    copy_inherited_fields(conn, id)
+}
+
+/// Copy the species' values of the fields a variety inherits.
+fn copy_inherited_fields(conn: &Connection, id: i64) -> Result<(), String> {
+    let assignments = INHERITED_FIELDS
+        .iter()
+        .map(|field| {
+            format!(
+                "{0} = CASE WHEN EXISTS(SELECT 1 FROM json_each(plants.inherited_fields) WHERE value = '{0}')
+                    THEN (SELECT {0} FROM plants AS species WHERE species.id = plants.parent_id) ELSE {0} END",
+                field
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    conn.execute(
+        &format!("UPDATE plants SET {} WHERE id = ?1 AND parent_id IS NOT NULL", assignments),
+        [id],
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
 
+/// Check that plant `id` (None for a new plant) can be a variety of `species_id`. Varieties go
+/// one level deep: a species can't be a variety itself.
+fn check_species(conn: &Connection, id: Option<i64>, species_id: i64) -> Result<(), String> {
+    if id == Some(species_id) {
+        return Err("A plant can't be a variety of itself".to_string());
+    }
+    let (species_name, species_parent_id): (String, Option<i64>) = conn
+        .query_row("SELECT name, parent_id FROM plants WHERE id = ?1", [species_id], |row| Ok((row.get(0)?, row.get(1)?)))
+        .optional()
+        .map_err(|e| e.to_string())?
+        .ok_or("The species no longer exists")?;
+    if species_parent_id.is_some() {
+        return Err(format!("{} is a variety itself, so it can't have varieties", species_name));
+    }
+    if let Some(id) = id {
+        let has_varieties: bool = conn
+            .query_row("SELECT EXISTS(SELECT 1 FROM plants WHERE parent_id = ?1)", [id], |row| row.get(0))
+            .map_err(|e| e.to_string())?;
+        if has_varieties {
+            return Err("This plant has varieties of its own, so it can't be a variety".to_string());
+        }
+    }
     Ok(())
 }
 
+/// A plant with its species and varieties. The plant's `inherited_fields` tell which of its
+/// values come from the species.
+#[derive(Debug, Serialize)]
+pub struct ResolvedPlant {
+    pub plant: Plant,
+    pub species: Option<Plant>,
+    pub varieties: Vec<Plant>,
+}
+
 #[tauri::command]
-pub fn get_plants_to_reorder(db: State<Database>) -> Result<Vec<Plant>, String> {
+pub fn resolve_plant(app: AppHandle, db: State<Database>, id: i64) -> Result<ResolvedPlant, String> {
+    let shift = period_shift(&app)?;
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
 
+    let mut plant = load_plant(&conn, id)?;
+    shift_plant(&mut plant, shift);
+    let mut species = plant.parent_id.map(|parent_id| load_plant(&conn, parent_id)).transpose()?;
+    if let Some(species) = &mut species {
+        shift_plant(species, shift);
+    }
+
     let mut stmt = conn
-        .prepare("SELECT id, name, plant_type, sun_requirements, sow_periods, plant_periods, notes, needs_reorder, created_at, updated_at FROM plants WHERE needs_reorder = 1 ORDER BY name")
+        .prepare(&format!("SELECT {} FROM plants WHERE parent_id = ?1 ORDER BY name", PLANT_COLUMNS))
         .map_err(|e| e.to_string())?;
-
-    let plants = stmt
-        .query_map([], |row| {
-            let needs_reorder: i32 = row.get(7)?;
-            Ok(Plant {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                plant_type: row.get(2)?,
-                sun_requirements: row.get(3)?,
-                sow_periods: row.get(4)?,
-                plant_periods: row.get(5)?,
-                notes: row.get(6)?,
-                needs_reorder: needs_reorder != 0,
-                created_at: row.get(8)?,
-                updated_at: row.get(9)?,
-            })
-        })
+    let mut varieties = stmt
+        .query_map([id], plant_from_row)
         .map_err(|e| e.to_string())?
         .collect::<Result<Vec<_>, _>>()
         .map_err(|e| e.to_string())?;
+    shift_plants(&mut varieties, shift);
 
-    Ok(plants)
+    Ok(ResolvedPlant {
+        plant,
+        species,
+        varieties,
+    })
 }
 
-#[tauri::command]
-pub fn get_all_activities(db: State<Database>) -> Result<Vec<Activity>, String> {
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+/// Split a "Species - Variety" name, like "Aardbei - Gele alpenaardbei", at the first " - ".
+fn split_variety_name(name: &str) -> Option<(&str, &str)> {
+    let (species, variety) = name.split_once(" - ")?;
+    let (species, variety) = (species.trim(), variety.trim());
+    (!species.is_empty() && !variety.is_empty()).then_some((species, variety))
+}
 
-    let mut stmt = conn
-        .prepare("SELECT id, name, description, active_periods, created_at, updated_at FROM activities ORDER BY name")
+/// The species called `name`, created without any values if there is none yet. Returns its id
+/// and whether it was created.
+fn find_or_create_species(conn: &Connection, name: &str) -> Result<(i64, bool), String> {
+    let existing: Option<i64> = conn
+        .query_row(
+            "SELECT id FROM plants WHERE name = ?1 COLLATE NOCASE AND parent_id IS NULL ORDER BY id LIMIT 1",
+            [name],
+            |row| row.get(0),
+        )
+        .optional()
         .map_err(|e| e.to_string())?;
+    match existing {
+        Some(id) => Ok((id, false)),
+        None => {
+            conn.execute("INSERT INTO plants (name) VALUES (?1)", [name])
+                .map_err(|e| e.to_string())?;
+            Ok((conn.last_insert_rowid(), true))
+        }
+    }
+}
 
-    let activities = stmt
-        .query_map([], |row| {
-            Ok(Activity {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                description: row.get(2)?,
-                active_periods: row.get(3)?,
-                created_at: row.get(4)?,
-                updated_at: row.get(5)?,
-            })
-        })
+/// Make plants named "Species - Variety" varieties of their species, creating species that
+/// don't exist yet. Plants that already belong to a species or have varieties are left alone.
+/// Returns the number of plants that became a variety.
+#[tauri::command]
+pub fn link_varieties_by_name(db: State<Database>) -> Result<u32, String> {
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+    let candidates: Vec<(i64, String)> = tx
+        .prepare(
+            "SELECT id, name FROM plants AS p
+             WHERE parent_id IS NULL AND name LIKE '% - %'
+               AND NOT EXISTS(SELECT 1 FROM plants AS v WHERE v.parent_id = p.id)
+             ORDER BY id",
+        )
+        .map_err(|e| e.to_string())?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
         .map_err(|e| e.to_string())?
         .collect::<Result<Vec<_>, _>>()
         .map_err(|e| e.to_string())?;
 
-    Ok(activities)
+    let mut linked = 0;
+    for (id, name) in candidates {
+        let Some((species, _)) = split_variety_name(&name) else {
+            continue;
+        };
+        let (species_id, _) = find_or_create_species(&tx, species)?;
+        tx.execute("UPDATE plants SET parent_id = ?1 WHERE id = ?2", [species_id, id])
+            .map_err(|e| e.to_string())?;
+        inherit_unset_fields(&tx, id)?;
+        linked += 1;
+    }
+
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(linked)
 }
 
 #[tauri::command]
-pub fn create_activity(db: State<Database>, activity: Activity) -> Result<Activity, String> {
+pub fn delete_plant(db: State<Database>, id: i64) -> Result<(), String> {
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
 
-    conn.execute(
-        "INSERT INTO activities (name, description, active_periods) VALUES (?1, ?2, ?3)",
-        (&activity.name, &activity.description, &activity.active_periods),
-    ).map_err(|e| e.to_string())?;
-
-    let id = conn.last_insert_rowid();
-
-    Ok(Activity {
-        id: Some(id),
-        ..activity
-    })
-}
+    // Photos of the plant's tasks go with them
+    let original_files = photo_original_files(
+        &conn,
+        "plant_id = ?1 OR plant_task_id IN (SELECT id FROM plant_tasks WHERE plant_id = ?1)",
+        id,
+    )?;
 
-#[tauri::command]
-pub fn update_activity(db: State<Database>, activity: Activity) -> Result<(), String> {
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    // Its varieties keep their values but no longer have a species to inherit from
+    conn.execute("UPDATE plants SET inherited_fields = '[]' WHERE parent_id = ?1", [id])
+        .map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM plants WHERE id = ?1", [id])
+        .map_err(|e| e.to_string())?;
 
-    conn.execute(
-        "UPDATE activities SET name = ?1, description = ?2, active_periods = ?3, updated_at = CURRENT_TIMESTAMP WHERE id = ?4",
-        (&activity.name, &activity.description, &activity.active_periods, &activity.id),
-    ).map_err(|e| e.to_string())?;
+    remove_unused_originals(&conn, &db.get_path(), &original_files).map_err(|e| e.to_string())?;
 
     Ok(())
 }
 
 #[tauri::command]
-pub fn delete_activity(db: State<Database>, id: i64) -> Result<(), String> {
+pub fn get_plants_to_reorder(app: AppHandle, db: State<Database>) -> Result<Vec<Plant>, String> {
+    let shift = period_shift(&app)?;
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
 
-    conn.execute("DELETE FROM activities WHERE id = ?1", [id])
+    let mut stmt = conn
+        .prepare(&format!("SELECT {} FROM plants WHERE needs_reorder = 1 ORDER BY name", PLANT_COLUMNS))
         .map_err(|e| e.to_string())?;
 
-    Ok(())
+    let mut plants = stmt
+        .query_map([], plant_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    shift_plants(&mut plants, shift);
+
+    Ok(plants)
 }
 
-#[derive(Debug, Serialize)]
-pub struct MonthData {
-    pub sow_early: Vec<Plant>,
-    pub sow_late: Vec<Plant>,
-    pub plant_early: Vec<Plant>,
-    pub plant_late: Vec<Plant>,
-    pub activities: Vec<Activity>,
+/// How a bitmask or a set of tags in a filter has to match: at least one of them, or all
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BitmaskMatch {
+    #[default]
+    Any,
+    All,
 }
 
-#[tauri::command]
-pub fn get_month_data(db: State<Database>, month: u32) -> Result<MonthData, String> {
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum PlantSortKey {
+    Name,
+    PlantType,
+    /// Earliest half-month the plant can be sown, plants without sowing periods last
+    FirstSowPeriod,
+    /// Earliest half-month the plant can be planted out, plants without planting periods last
+    FirstPlantPeriod,
+    LastPrice,
+    CreatedAt,
+    UpdatedAt,
+}
 
-    let early_bit = 1 << ((month - 1) * 2);
-    let late_bit = 1 << ((month - 1) * 2 + 1);
+impl PlantSortKey {
+    /// Sort expression, with periods shifted `shift` half-months for the garden's location
+    fn sql(self, shift: i32) -> String {
+        let first_period = |column: &str| {
+            let periods = shift_periods_sql(column, shift);
+            format!("CASE WHEN {0} = 0 THEN 1 << 30 ELSE {1} & -{1} END", column, periods)
+        };
+        match self {
+            PlantSortKey::Name => "name COLLATE NOCASE".to_string(),
+            // Category order, uncategorized plants last
+            PlantSortKey::PlantType => "COALESCE((SELECT sort_order FROM plant_categories c WHERE c.key = plant_type), 1 << 30)".to_string(),
+            PlantSortKey::FirstSowPeriod => first_period("sow_periods"),
+            PlantSortKey::FirstPlantPeriod => first_period("plant_periods"),
+            PlantSortKey::LastPrice => "last_price".to_string(),
+            PlantSortKey::CreatedAt => "created_at".to_string(),
+            PlantSortKey::UpdatedAt => "updated_at".to_string(),
+        }
+    }
+}
 
-    let get_plants = |sql: &str| -> Result<Vec<Plant>, String> {
-        let mut stmt = conn.prepare(sql).map_err(|e| e.to_string())?;
-        let result = stmt.query_map([], |row| {
-            let needs_reorder: i32 = row.get(7)?;
-            Ok(Plant {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                plant_type: row.get(2)?,
-                sun_requirements: row.get(3)?,
-                sow_periods: row.get(4)?,
-                plant_periods: row.get(5)?,
-                notes: row.get(6)?,
-                needs_reorder: needs_reorder != 0,
-                created_at: row.get(8)?,
-                updated_at: row.get(9)?,
-            })
-        })
-        .map_err(|e| e.to_string())?
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| e.to_string());
-        result
-    };
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PlantSort {
+    pub key: PlantSortKey,
+    #[serde(default)]
+    pub descending: bool,
+}
 
-    let sow_early = get_plants(&format!(
-        "SELECT id, name, plant_type, sun_requirements, sow_periods, plant_periods, notes, needs_reorder, created_at, updated_at FROM plants WHERE (sow_periods & {}) != 0 ORDER BY name",
-        early_bit
-    ))?;
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct PlantFilter {
+    /// Any of these category keys; empty matches all
+    pub plant_types: Vec<String>,
+    /// Sun requirements bitmask, 0 matches all
+    pub sun_requirements: i32,
+    pub sun_match: BitmaskMatch,
+    /// Plants whose sowing periods overlap this half-month bitmask, 0 matches all
+    pub sow_periods: i32,
+    /// Plants whose planting periods overlap this half-month bitmask, 0 matches all
+    pub plant_periods: i32,
+    pub needs_reorder: Option<bool>,
+    /// Tag ids, empty matches all
+    pub tag_ids: Vec<i64>,
+    pub tag_match: BitmaskMatch,
+    /// Words to find in the name, botanical names or notes, using the search index
+    pub text: Option<String>,
+    /// Inclusive date ranges, YYYY-MM-DD
+    pub created_from: Option<String>,
+    pub created_to: Option<String>,
+    pub updated_from: Option<String>,
+    pub updated_to: Option<String>,
+    /// Sort keys in order of precedence; by name when empty
+    pub sort: Vec<PlantSort>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
 
-    let sow_late = get_plants(&format!(
-        "SELECT id, name, plant_type, sun_requirements, sow_periods, plant_periods, notes, needs_reorder, created_at, updated_at FROM plants WHERE (sow_periods & {}) != 0 ORDER BY name",
-        late_bit
-    ))?;
+#[derive(Debug, Serialize)]
+pub struct PlantPage {
+    pub plants: Vec<Plant>,
+    /// Number of plants matching the filter, ignoring limit and offset
+    pub total: i64,
+}
 
-    let plant_early = get_plants(&format!(
-        "SELECT id, name, plant_type, sun_requirements, sow_periods, plant_periods, notes, needs_reorder, created_at, updated_at FROM plants WHERE (plant_periods & {}) != 0 ORDER BY name",
-        early_bit
-    ))?;
+/// Run a plant query. Periods in the filter and in the plants are shifted `shift` half-months
+/// from the stored ones, see `period_shift`.
+fn run_plant_query(conn: &Connection, filter: &PlantFilter, shift: i32) -> Result<PlantPage, String> {
+    let mut conditions = vec!["1 = 1".to_string()];
+    let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+
+    if !filter.plant_types.is_empty() {
+        let mut placeholders = Vec::new();
+        for plant_type in &filter.plant_types {
+            params.push(Box::new(plant_type.clone()));
+            placeholders.push(format!("?{}", params.len()));
+        }
+        conditions.push(format!("plant_type IN ({})", placeholders.join(", ")));
+    }
+    if filter.sun_requirements != 0 {
+        params.push(Box::new(filter.sun_requirements));
+        conditions.push(match filter.sun_match {
+            BitmaskMatch::Any => format!("(sun_requirements & ?{}) != 0", params.len()),
+            BitmaskMatch::All => format!("(sun_requirements & ?{0}) = ?{0}", params.len()),
+        });
+    }
+    if filter.sow_periods != 0 {
+        params.push(Box::new(shift_periods(filter.sow_periods, -shift)));
+        conditions.push(format!("(sow_periods & ?{}) != 0", params.len()));
+    }
+    if filter.plant_periods != 0 {
+        params.push(Box::new(shift_periods(filter.plant_periods, -shift)));
+        conditions.push(format!("(plant_periods & ?{}) != 0", params.len()));
+    }
+    if let Some(needs_reorder) = filter.needs_reorder {
+        params.push(Box::new(needs_reorder));
+        conditions.push(format!("needs_reorder = ?{}", params.len()));
+    }
+    if !filter.tag_ids.is_empty() {
+        let mut tag_ids = filter.tag_ids.clone();
+        tag_ids.sort_unstable();
+        tag_ids.dedup();
+        let tagged = format!(
+            "(SELECT COUNT(*) FROM plant_tags WHERE plant_id = plants.id AND tag_id IN ({}))",
+            tag_ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(", ")
+        );
+        conditions.push(match filter.tag_match {
+            BitmaskMatch::Any => format!("{} > 0", tagged),
+            BitmaskMatch::All => format!("{} = {}", tagged, tag_ids.len()),
+        });
+    }
+    if let Some(query) = filter.text.as_deref().and_then(fts_query) {
+        params.push(Box::new(query));
+        conditions.push(format!(
+            "id IN (SELECT entity_id FROM search_index WHERE search_index MATCH ?{} AND entity_type = 'plant')",
+            params.len()
+        ));
+    }
+    // Timestamps are stored as "YYYY-MM-DD HH:MM:SS", compare on the date part
+    let date_ranges = [
+        ("created_at", ">=", &filter.created_from),
+        ("created_at", "<=", &filter.created_to),
+        ("updated_at", ">=", &filter.updated_from),
+        ("updated_at", "<=", &filter.updated_to),
+    ];
+    for (column, operator, date) in date_ranges {
+        if let Some(date) = date.as_ref().filter(|d| !d.is_empty()) {
+            params.push(Box::new(date.clone()));
+            conditions.push(format!("date({}) {} ?{}", column, operator, params.len()));
+        }
+    }
 
-    let plant_late = get_plants(&format!(
-        "SELECT id, name, plant_type, sun_requirements, sow_periods, plant_periods, notes, needs_reorder, created_at, updated_at FROM plants WHERE (plant_periods & {}) != 0 ORDER BY name",
-        late_bit
-    ))?;
+    let condition = conditions.join(" AND ");
+    let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
 
-    let month_bits = early_bit | late_bit;
-    let mut stmt = conn
-        .prepare(&format!(
-            "SELECT id, name, description, active_periods, created_at, updated_at FROM activities WHERE (active_periods & {}) != 0 ORDER BY name",
-            month_bits
-        ))
+    let total: i64 = conn
+        .query_row(&format!("SELECT COUNT(*) FROM plants WHERE {}", condition), param_refs.as_slice(), |row| row.get(0))
         .map_err(|e| e.to_string())?;
 
-    let activities = stmt
-        .query_map([], |row| {
-            Ok(Activity {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                description: row.get(2)?,
-                active_periods: row.get(3)?,
-                created_at: row.get(4)?,
-                updated_at: row.get(5)?,
-            })
-        })
+    let mut order_by: Vec<String> = filter
+        .sort
+        .iter()
+        .map(|sort| format!("{} {}", sort.key.sql(shift), if sort.descending { "DESC" } else { "ASC" }))
+        .collect();
+    if order_by.is_empty() {
+        order_by.push(PlantSortKey::Name.sql(shift));
+    }
+    // Stable order, so pages don't overlap
+    order_by.push("id".to_string());
+
+    // SQLite needs a LIMIT before an OFFSET; -1 means no limit
+    let limit = filter.limit.map(i64::from).unwrap_or(-1);
+    let offset = filter.offset.unwrap_or(0);
+    let sql = format!(
+        "SELECT {} FROM plants WHERE {} ORDER BY {} LIMIT {} OFFSET {}",
+        PLANT_COLUMNS,
+        condition,
+        order_by.join(", "),
+        limit,
+        offset
+    );
+
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let mut plants = stmt
+        .query_map(param_refs.as_slice(), plant_from_row)
         .map_err(|e| e.to_string())?
         .collect::<Result<Vec<_>, _>>()
         .map_err(|e| e.to_string())?;
+    shift_plants(&mut plants, shift);
 
-    Ok(MonthData {
-        sow_early,
-        sow_late,
-        plant_early,
-        plant_late,
-        activities,
+    Ok(PlantPage { plants, total })
+}
+
+/// Plants matching `filter`, sorted and paginated in the database.
+#[tauri::command]
+pub fn query_plants(app: AppHandle, db: State<Database>, filter: Option<PlantFilter>) -> Result<PlantPage, String> {
+    let shift = period_shift(&app)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    run_plant_query(&conn, &filter.unwrap_or_default(), shift)
+}
+
+/// A named plant query saved in the database
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SmartList {
+    pub id: Option<i64>,
+    pub name: String,
+    pub filter: PlantFilter,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+fn smart_list_from_row(row: &rusqlite::Row) -> rusqlite::Result<SmartList> {
+    Ok(SmartList {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        // A filter saved by a newer version may have fields this one doesn't know; those are
+        // ignored. A filter that can't be read at all is an error rather than matching everything.
+        filter: json_from_row(row, 2)?,
+        created_at: row.get(3)?,
+        updated_at: row.get(4)?,
     })
 }
 
-#[tauri::command(rename_all = "camelCase")]
-pub fn get_photos(db: State<Database>, plant_id: i64) -> Result<Vec<PlantPhoto>, String> {
+#[tauri::command]
+pub fn get_smart_lists(db: State<Database>) -> Result<Vec<SmartList>, String> {
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
 
     let mut stmt = conn
-        .prepare("SELECT id, plant_id, sort_order, image_data, created_at FROM plant_photos WHERE plant_id = ?1 ORDER BY sort_order")
+        .prepare("SELECT id, name, filter, created_at, updated_at FROM smart_lists ORDER BY name COLLATE NOCASE")
         .map_err(|e| e.to_string())?;
 
-    let photos = stmt
-        .query_map([plant_id], |row| {
-            let image_blob: Option<Vec<u8>> = row.get(3)?;
-            let image_data = image_blob.map(|b| STANDARD.encode(&b));
-
-            Ok(PlantPhoto {
-                id: row.get(0)?,
-                plant_id: row.get(1)?,
-                sort_order: row.get(2)?,
-                image_data,
-                created_at: row.get(4)?,
-            })
-        })
+    let smart_lists = stmt
+        .query_map([], smart_list_from_row)
         .map_err(|e| e.to_string())?
         .collect::<Result<Vec<_>, _>>()
         .map_err(|e| e.to_string())?;
 
-    Ok(photos)
+    Ok(smart_lists)
 }
 
 #[tauri::command(rename_all = "camelCase")]
-pub fn add_photo(db: State<Database>, plant_id: i64, image_data: String, sort_order: i32) -> Result<PlantPhoto, String> {
+pub fn create_smart_list(db: State<Database>, mut smart_list: SmartList) -> Result<SmartList, String> {
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
 
-    let image_bytes = STANDARD.decode(&image_data).map_err(|e| e.to_string())?;
+    smart_list.name = smart_list.name.trim().to_string();
+    if smart_list.name.is_empty() {
+        return Err("A smart list needs a name".to_string());
+    }
+    // Pagination belongs to running the list, not to the list itself
+    smart_list.filter.limit = None;
+    smart_list.filter.offset = None;
+    let filter = serde_json::to_string(&smart_list.filter).map_err(|e| e.to_string())?;
 
     conn.execute(
-        "INSERT INTO plant_photos (plant_id, sort_order, image_data) VALUES (?1, ?2, ?3)",
-        rusqlite::params![plant_id, sort_order, image_bytes],
+        "INSERT INTO smart_lists (name, filter) VALUES (?1, ?2)",
+        rusqlite::params![&smart_list.name, &filter],
     ).map_err(|e| e.to_string())?;
 
     let id = conn.last_insert_rowid();
+    conn.query_row(
+        "SELECT id, name, filter, created_at, updated_at FROM smart_lists WHERE id = ?1",
+        [id],
+        smart_list_from_row,
+    ).map_err(|e| e.to_string())
+}
 
-    Ok(PlantPhoto {
-        id: Some(id),
-        plant_id,
-        sort_order,
-        image_data: Some(image_data),
-        created_at: None,
-    })
+/// Plants on a smart list, with optional pagination.
+#[tauri::command]
+pub fn run_smart_list(app: AppHandle, db: State<Database>, id: i64, limit: Option<u32>, offset: Option<u32>) -> Result<PlantPage, String> {
+    let shift = period_shift(&app)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    let smart_list = conn
+        .query_row(
+            "SELECT id, name, filter, created_at, updated_at FROM smart_lists WHERE id = ?1",
+            [id],
+            smart_list_from_row,
+        )
+        .map_err(|e| e.to_string())?;
+
+    let filter = PlantFilter {
+        limit,
+        offset,
+        ..smart_list.filter
+    };
+    run_plant_query(&conn, &filter, shift)
 }
 
 #[tauri::command]
-pub fn delete_photo(db: State<Database>, id: i64) -> Result<(), String> {
+pub fn delete_smart_list(db: State<Database>, id: i64) -> Result<(), String> {
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
 
-    conn.execute("DELETE FROM plant_photos WHERE id = ?1", [id])
+    conn.execute("DELETE FROM smart_lists WHERE id = ?1", [id])
         .map_err(|e| e.to_string())?;
 
     Ok(())
 }
 
-#[tauri::command]
-pub fn import_plants_tsv(db: State<Database>, tsv_content: String) -> Result<u32, String> {
+// Qualified, since due tasks are selected together with their plant
+const PLANT_TASK_COLUMNS: &str = "plant_tasks.id, plant_tasks.plant_id, plant_tasks.name, plant_tasks.periods, plant_tasks.interval_years,
+    plant_tasks.last_done_year, plant_tasks.created_at, plant_tasks.updated_at";
+
+fn plant_task_from_row(row: &rusqlite::Row) -> rusqlite::Result<PlantTask> {
+    Ok(PlantTask {
+        id: row.get(0)?,
+        plant_id: row.get(1)?,
+        name: row.get(2)?,
+        periods: row.get(3)?,
+        interval_years: row.get(4)?,
+        last_done_year: row.get(5)?,
+        created_at: row.get(6)?,
+        updated_at: row.get(7)?,
+    })
+}
+
+fn check_plant_task(task: &mut PlantTask) -> Result<(), String> {
+    task.name = task.name.trim().to_string();
+    if task.name.is_empty() {
+        return Err("A task needs a name".to_string());
+    }
+    if task.interval_years < 1 {
+        return Err("A task needs an interval of at least one year".to_string());
+    }
+    Ok(())
+}
+
+/// A plant's tasks. Their periods are stored for the reference climate, like plant periods.
+#[tauri::command(rename_all = "camelCase")]
+pub fn get_plant_tasks(app: AppHandle, db: State<Database>, plant_id: i64) -> Result<Vec<PlantTask>, String> {
+    let shift = period_shift(&app)?;
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
 
-    let lines: Vec<&str> = tsv_content.lines().collect();
-    if lines.is_empty() {
-        return Ok(0);
+    let mut stmt = conn
+        .prepare(&format!("SELECT {} FROM plant_tasks WHERE plant_id = ?1 ORDER BY name", PLANT_TASK_COLUMNS))
+        .map_err(|e| e.to_string())?;
+
+    let mut tasks = stmt
+        .query_map([plant_id], plant_task_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    for task in &mut tasks {
+        task.periods = shift_periods(task.periods, shift);
     }
 
-    let mut imported = 0;
+    Ok(tasks)
+}
 
-    // Skip header row (line 0)
-    for line in lines.iter().skip(1) {
-        let cols: Vec<&str> = line.split('\t').collect();
-        if cols.is_empty() || cols[0].trim().is_empty() {
-            continue;
-        }
+#[tauri::command]
+pub fn create_plant_task(app: AppHandle, db: State<Database>, mut task: PlantTask) -> Result<PlantTask, String> {
+    let shift = period_shift(&app)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
 
-        let name = cols[0].trim().to_string();
+    check_plant_task(&mut task)?;
+    conn.execute(
+        "INSERT INTO plant_tasks (plant_id, name, periods, interval_years, last_done_year) VALUES (?1, ?2, ?3, ?4, ?5)",
+        (&task.plant_id, &task.name, &shift_periods(task.periods, -shift), &task.interval_years, &task.last_done_year),
+    ).map_err(|e| e.to_string())?;
+
+    Ok(PlantTask {
+        id: Some(conn.last_insert_rowid()),
+        ..task
+    })
+}
+
+#[tauri::command]
+pub fn update_plant_task(app: AppHandle, db: State<Database>, mut task: PlantTask) -> Result<(), String> {
+    let shift = period_shift(&app)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    check_plant_task(&mut task)?;
+    conn.execute(
+        "UPDATE plant_tasks SET name = ?1, periods = ?2, interval_years = ?3, last_done_year = ?4, updated_at = CURRENT_TIMESTAMP WHERE id = ?5",
+        (&task.name, &shift_periods(task.periods, -shift), &task.interval_years, &task.last_done_year, &task.id),
+    ).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Mark a task as done in `year`, this year by default.
+#[tauri::command]
+pub fn complete_plant_task(db: State<Database>, id: i64, year: Option<i32>) -> Result<(), String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    let year = year.unwrap_or_else(|| Local::now().year());
+    conn.execute(
+        "UPDATE plant_tasks SET last_done_year = ?1, updated_at = CURRENT_TIMESTAMP WHERE id = ?2",
+        [year as i64, id],
+    ).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn delete_plant_task(db: State<Database>, id: i64) -> Result<(), String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    let original_files = photo_original_files(&conn, "plant_task_id = ?1", id)?;
+
+    conn.execute("DELETE FROM plant_tasks WHERE id = ?1", [id])
+        .map_err(|e| e.to_string())?;
+
+    remove_unused_originals(&conn, &db.get_path(), &original_files).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Condition for a plant task that is due in `year`: it was never done and the plant's
+/// establishment year is unknown, or its interval has passed since it was last done or, before
+/// that, since the plant was established.
+const PLANT_TASK_DUE: &str = "(COALESCE(plant_tasks.last_done_year, plants.established_year) IS NULL
+    OR ?1 >= COALESCE(plant_tasks.last_done_year, plants.established_year) + plant_tasks.interval_years)";
+
+const ACTIVITY_COLUMNS: &str = "id, name, description, active_periods, created_at, updated_at,
+    (SELECT json_group_array(tag_id) FROM activity_tags WHERE activity_id = activities.id)";
+
+fn activity_from_row(row: &rusqlite::Row) -> rusqlite::Result<Activity> {
+    Ok(Activity {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        description: row.get(2)?,
+        active_periods: row.get(3)?,
+        created_at: row.get(4)?,
+        updated_at: row.get(5)?,
+        tag_ids: Some(json_from_row(row, 6)?),
+    })
+}
+
+#[tauri::command]
+pub fn get_all_activities(app: AppHandle, db: State<Database>) -> Result<Vec<Activity>, String> {
+    let shift = period_shift(&app)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare(&format!("SELECT {} FROM activities ORDER BY name", ACTIVITY_COLUMNS))
+        .map_err(|e| e.to_string())?;
+
+    let mut activities = stmt
+        .query_map([], activity_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    for activity in &mut activities {
+        activity.active_periods = shift_periods(activity.active_periods, shift);
+    }
+
+    Ok(activities)
+}
+
+/// Activity periods are stored for the reference climate, like plant periods.
+#[tauri::command]
+pub fn create_activity(app: AppHandle, db: State<Database>, activity: Activity) -> Result<Activity, String> {
+    let shift = period_shift(&app)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "INSERT INTO activities (name, description, active_periods) VALUES (?1, ?2, ?3)",
+        (&activity.name, &activity.description, &shift_periods(activity.active_periods, -shift)),
+    ).map_err(|e| e.to_string())?;
+
+    let id = conn.last_insert_rowid();
+    if let Some(tag_ids) = &activity.tag_ids {
+        set_tags(&conn, "activity_tags", "activity_id", id, tag_ids)?;
+    }
+
+    Ok(Activity {
+        id: Some(id),
+        ..activity
+    })
+}
+
+#[tauri::command]
+pub fn update_activity(app: AppHandle, db: State<Database>, activity: Activity) -> Result<(), String> {
+    let shift = period_shift(&app)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "UPDATE activities SET name = ?1, description = ?2, active_periods = ?3, updated_at = CURRENT_TIMESTAMP WHERE id = ?4",
+        (&activity.name, &activity.description, &shift_periods(activity.active_periods, -shift), &activity.id),
+    ).map_err(|e| e.to_string())?;
+
+    if let (Some(id), Some(tag_ids)) = (activity.id, &activity.tag_ids) {
+        set_tags(&conn, "activity_tags", "activity_id", id, tag_ids)?;
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn delete_activity(db: State<Database>, id: i64) -> Result<(), String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    let original_files = photo_original_files(&conn, "activity_id = ?1", id)?;
+
+    conn.execute("DELETE FROM activities WHERE id = ?1", [id])
+        .map_err(|e| e.to_string())?;
+
+    remove_unused_originals(&conn, &db.get_path(), &original_files).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+fn tag_from_row(row: &rusqlite::Row) -> rusqlite::Result<Tag> {
+    Ok(Tag {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        color: row.get(2)?,
+        created_at: row.get(3)?,
+        updated_at: row.get(4)?,
+    })
+}
+
+#[tauri::command]
+pub fn get_tags(db: State<Database>) -> Result<Vec<Tag>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare("SELECT id, name, color, created_at, updated_at FROM tags ORDER BY name COLLATE NOCASE")
+        .map_err(|e| e.to_string())?;
+
+    let tags = stmt
+        .query_map([], tag_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(tags)
+}
+
+/// Tag names are unique regardless of case.
+fn check_tag_name(conn: &Connection, name: &str, id: Option<i64>) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("A tag needs a name".to_string());
+    }
+    let taken: bool = conn
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM tags WHERE name = ?1 COLLATE NOCASE AND id IS NOT ?2)",
+            rusqlite::params![name, id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if taken {
+        return Err(format!("There is already a tag called \"{}\"", name));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn create_tag(db: State<Database>, tag: Tag) -> Result<Tag, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    let name = tag.name.trim().to_string();
+    check_tag_name(&conn, &name, None)?;
+
+    conn.execute(
+        "INSERT INTO tags (name, color) VALUES (?1, ?2)",
+        rusqlite::params![&name, &tag.color],
+    ).map_err(|e| e.to_string())?;
+
+    let id = conn.last_insert_rowid();
+    conn.query_row(
+        "SELECT id, name, color, created_at, updated_at FROM tags WHERE id = ?1",
+        [id],
+        tag_from_row,
+    ).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn update_tag(db: State<Database>, tag: Tag) -> Result<(), String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    let name = tag.name.trim();
+    check_tag_name(&conn, name, tag.id)?;
+
+    conn.execute(
+        "UPDATE tags SET name = ?1, color = ?2, updated_at = CURRENT_TIMESTAMP WHERE id = ?3",
+        rusqlite::params![name, &tag.color, &tag.id],
+    ).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Delete a tag and remove it from every plant and activity.
+#[tauri::command]
+pub fn delete_tag(db: State<Database>, id: i64) -> Result<(), String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    conn.execute("DELETE FROM tags WHERE id = ?1", [id])
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub fn set_plant_tags(db: State<Database>, plant_id: i64, tag_ids: Vec<i64>) -> Result<(), String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    set_tags(&conn, "plant_tags", "plant_id", plant_id, &tag_ids)
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub fn set_activity_tags(db: State<Database>, activity_id: i64, tag_ids: Vec<i64>) -> Result<(), String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    set_tags(&conn, "activity_tags", "activity_id", activity_id, &tag_ids)
+}
+
+#[tauri::command]
+pub fn get_all_suppliers(db: State<Database>) -> Result<Vec<Supplier>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare("SELECT id, name, website, email, notes, created_at, updated_at FROM suppliers ORDER BY name")
+        .map_err(|e| e.to_string())?;
+
+    let suppliers = stmt
+        .query_map([], |row| {
+            Ok(Supplier {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                website: row.get(2)?,
+                email: row.get(3)?,
+                notes: row.get(4)?,
+                created_at: row.get(5)?,
+                updated_at: row.get(6)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(suppliers)
+}
+
+#[tauri::command]
+pub fn create_supplier(db: State<Database>, supplier: Supplier) -> Result<Supplier, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "INSERT INTO suppliers (name, website, email, notes) VALUES (?1, ?2, ?3, ?4)",
+        (&supplier.name, &supplier.website, &supplier.email, &supplier.notes),
+    ).map_err(|e| e.to_string())?;
+
+    let id = conn.last_insert_rowid();
+
+    Ok(Supplier {
+        id: Some(id),
+        ..supplier
+    })
+}
+
+#[tauri::command]
+pub fn update_supplier(db: State<Database>, supplier: Supplier) -> Result<(), String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "UPDATE suppliers SET name = ?1, website = ?2, email = ?3, notes = ?4, updated_at = CURRENT_TIMESTAMP WHERE id = ?5",
+        (&supplier.name, &supplier.website, &supplier.email, &supplier.notes, &supplier.id),
+    ).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn delete_supplier(db: State<Database>, id: i64) -> Result<(), String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    conn.execute("DELETE FROM suppliers WHERE id = ?1", [id])
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+const CATEGORY_COLUMNS: &str = "id, key, name, color, icon, sort_order, created_at, updated_at";
+
+fn category_from_row(row: &rusqlite::Row) -> rusqlite::Result<PlantCategory> {
+    Ok(PlantCategory {
+        id: row.get(0)?,
+        key: row.get(1)?,
+        name: row.get(2)?,
+        color: row.get(3)?,
+        icon: row.get(4)?,
+        sort_order: row.get(5)?,
+        created_at: row.get(6)?,
+        updated_at: row.get(7)?,
+    })
+}
+
+fn query_categories(conn: &Connection) -> Result<Vec<PlantCategory>, String> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT {} FROM plant_categories ORDER BY sort_order, name", CATEGORY_COLUMNS))
+        .map_err(|e| e.to_string())?;
+
+    let categories = stmt
+        .query_map([], category_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(categories)
+}
+
+/// A key for a new category: the name in lowercase with underscores, made unique with a number.
+fn category_key(conn: &Connection, name: &str) -> Result<String, String> {
+    let base: String = name
+        .trim()
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("_");
+    let base = if base.is_empty() { "category".to_string() } else { base };
+
+    let mut key = base.clone();
+    let mut suffix = 2;
+    loop {
+        let exists: bool = conn
+            .query_row("SELECT EXISTS(SELECT 1 FROM plant_categories WHERE key = ?1)", [&key], |row| row.get(0))
+            .map_err(|e| e.to_string())?;
+        if !exists {
+            return Ok(key);
+        }
+        key = format!("{}_{}", base, suffix);
+        suffix += 1;
+    }
+}
+
+#[tauri::command]
+pub fn get_plant_categories(db: State<Database>) -> Result<Vec<PlantCategory>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    query_categories(&conn)
+}
+
+#[tauri::command]
+pub fn create_plant_category(db: State<Database>, mut category: PlantCategory) -> Result<PlantCategory, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    category.name = category.name.trim().to_string();
+    if category.name.is_empty() {
+        return Err("A category needs a name".to_string());
+    }
+    category.key = category_key(&conn, &category.name)?;
+
+    // New categories go at the end
+    conn.execute(
+        "INSERT INTO plant_categories (key, name, color, icon, sort_order)
+         VALUES (?1, ?2, ?3, ?4, (SELECT COALESCE(MAX(sort_order) + 1, 0) FROM plant_categories))",
+        rusqlite::params![&category.key, &category.name, &category.color, &category.icon],
+    ).map_err(|e| e.to_string())?;
+
+    let id = conn.last_insert_rowid();
+    conn.query_row(
+        &format!("SELECT {} FROM plant_categories WHERE id = ?1", CATEGORY_COLUMNS),
+        [id],
+        category_from_row,
+    ).map_err(|e| e.to_string())
+}
+
+/// Update a category's name, colour and icon. The key doesn't change, so plants keep their category.
+#[tauri::command]
+pub fn update_plant_category(db: State<Database>, category: PlantCategory) -> Result<(), String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    let name = category.name.trim();
+    if name.is_empty() {
+        return Err("A category needs a name".to_string());
+    }
+
+    conn.execute(
+        "UPDATE plant_categories SET name = ?1, color = ?2, icon = ?3, updated_at = CURRENT_TIMESTAMP WHERE id = ?4",
+        rusqlite::params![name, &category.color, &category.icon, &category.id],
+    ).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Store the order of the categories: `category_ids` from first to last.
+#[tauri::command(rename_all = "camelCase")]
+pub fn reorder_plant_categories(db: State<Database>, category_ids: Vec<i64>) -> Result<(), String> {
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    for (index, id) in category_ids.iter().enumerate() {
+        tx.execute(
+            "UPDATE plant_categories SET sort_order = ?1 WHERE id = ?2",
+            rusqlite::params![index as i32, id],
+        ).map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Delete a category. Its plants are kept, without a category.
+#[tauri::command]
+pub fn delete_plant_category(db: State<Database>, id: i64) -> Result<(), String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    conn.execute("DELETE FROM plant_categories WHERE id = ?1", [id])
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+pub struct MonthData {
+    pub sow_early: Vec<Plant>,
+    pub sow_late: Vec<Plant>,
+    pub plant_early: Vec<Plant>,
+    pub plant_late: Vec<Plant>,
+    pub activities: Vec<Activity>,
+    /// Journal entries from the same month last year, as a reminder of what happened then
+    pub last_year_journal: Vec<JournalEntry>,
+    /// Categories in their configured order, for grouping the plants
+    pub categories: Vec<PlantCategory>,
+    /// Plant tasks in this month that are due this year
+    pub plant_tasks: Vec<DuePlantTask>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DuePlantTask {
+    pub task: PlantTask,
+    pub plant_name: String,
+}
+
+/// Condition limiting plants or activities to those with at least one of the tags, or no
+/// condition when no tags are given.
+fn tagged_condition(link_table: &str, owner_column: &str, owner_table: &str, tag_ids: &[i64]) -> String {
+    if tag_ids.is_empty() {
+        return String::new();
+    }
+    format!(
+        " AND EXISTS(SELECT 1 FROM {0} WHERE {0}.{1} = {2}.id AND tag_id IN ({3}))",
+        link_table,
+        owner_column,
+        owner_table,
+        tag_ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(", ")
+    )
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub fn get_month_data(app: AppHandle, db: State<Database>, month: u32, tag_ids: Option<Vec<i64>>) -> Result<MonthData, String> {
+    let shift = period_shift(&app)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    let early_bit = 1 << ((month - 1) * 2);
+    let late_bit = 1 << ((month - 1) * 2 + 1);
+    // The same half-months in the periods plants are stored with
+    let stored_early_bit = shift_periods(early_bit, -shift);
+    let stored_late_bit = shift_periods(late_bit, -shift);
+    let tag_ids = tag_ids.unwrap_or_default();
+    let plant_tagged = tagged_condition("plant_tags", "plant_id", "plants", &tag_ids);
+
+    let get_plants = |sql: &str| -> Result<Vec<Plant>, String> {
+        let mut stmt = conn.prepare(sql).map_err(|e| e.to_string())?;
+        let mut plants = stmt.query_map([], plant_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+        shift_plants(&mut plants, shift);
+        Ok(plants)
+    };
+
+    let sow_early = get_plants(&format!(
+        "SELECT {} FROM plants WHERE (sow_periods & {}) != 0{} ORDER BY name",
+        PLANT_COLUMNS, stored_early_bit, plant_tagged
+    ))?;
+
+    let sow_late = get_plants(&format!(
+        "SELECT {} FROM plants WHERE (sow_periods & {}) != 0{} ORDER BY name",
+        PLANT_COLUMNS, stored_late_bit, plant_tagged
+    ))?;
+
+    let plant_early = get_plants(&format!(
+        "SELECT {} FROM plants WHERE (plant_periods & {}) != 0{} ORDER BY name",
+        PLANT_COLUMNS, stored_early_bit, plant_tagged
+    ))?;
+
+    let plant_late = get_plants(&format!(
+        "SELECT {} FROM plants WHERE (plant_periods & {}) != 0{} ORDER BY name",
+        PLANT_COLUMNS, stored_late_bit, plant_tagged
+    ))?;
+
+    let month_bits = early_bit | late_bit;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM activities WHERE ({} & {}) != 0{} ORDER BY name",
+            ACTIVITY_COLUMNS,
+            shift_periods_sql("activities.active_periods", shift),
+            month_bits,
+            tagged_condition("activity_tags", "activity_id", "activities", &tag_ids)
+        ))
+        .map_err(|e| e.to_string())?;
+
+    let mut activities = stmt
+        .query_map([], activity_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    for activity in &mut activities {
+        activity.active_periods = shift_periods(activity.active_periods, shift);
+    }
+
+    let last_year_month = format!("{:04}-{:02}", Local::now().year() - 1, month);
+    let last_year_journal = query_journal(
+        &conn,
+        "substr(entry_date, 1, 7) = ?1 ORDER BY entry_date, id",
+        &[&last_year_month],
+    )?;
+
+    let categories = query_categories(&conn)?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {}, plants.name FROM plant_tasks JOIN plants ON plants.id = plant_tasks.plant_id
+             WHERE ({} & {}) != 0 AND {}{} ORDER BY plants.name, plant_tasks.name",
+            PLANT_TASK_COLUMNS,
+            shift_periods_sql("plant_tasks.periods", shift),
+            month_bits,
+            PLANT_TASK_DUE,
+            plant_tagged
+        ))
+        .map_err(|e| e.to_string())?;
+    let plant_tasks = stmt
+        .query_map([Local::now().year()], |row| {
+            let mut task = plant_task_from_row(row)?;
+            task.periods = shift_periods(task.periods, shift);
+            Ok(DuePlantTask {
+                task,
+                plant_name: row.get(8)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(MonthData {
+        sow_early,
+        sow_late,
+        plant_early,
+        plant_late,
+        activities,
+        last_year_journal,
+        categories,
+        plant_tasks,
+    })
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct BloomingPlant {
+    pub id: i64,
+    pub name: String,
+    pub flower_color: Option<String>,
+}
+
+/// The plants flowering in one half-month period
+#[derive(Debug, Serialize)]
+pub struct BloomPeriod {
+    /// Period bit, 0 for early January through 23 for late December
+    pub period: u32,
+    pub label: String,
+    pub plants: Vec<BloomingPlant>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BloomCalendar {
+    pub periods: Vec<BloomPeriod>,
+    /// Periods in which nothing flowers
+    pub gaps: Vec<u32>,
+}
+
+/// What flowers when, in half-month periods, limited to plants with one of the tags (e.g. the
+/// tag of a bed) when any are given.
+#[tauri::command(rename_all = "camelCase")]
+pub fn get_bloom_calendar(app: AppHandle, db: State<Database>, tag_ids: Option<Vec<i64>>) -> Result<BloomCalendar, String> {
+    let shift = period_shift(&app)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT id, name, flower_color, bloom_periods FROM plants WHERE bloom_periods != 0{} ORDER BY name",
+            tagged_condition("plant_tags", "plant_id", "plants", &tag_ids.unwrap_or_default())
+        ))
+        .map_err(|e| e.to_string())?;
+    let plants = stmt
+        .query_map([], |row| {
+            Ok((
+                BloomingPlant {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    flower_color: row.get(2)?,
+                },
+                shift_periods(row.get(3)?, shift),
+            ))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut periods: Vec<BloomPeriod> = (0..24)
+        .map(|period| BloomPeriod {
+            period,
+            label: period_label(period),
+            plants: Vec::new(),
+        })
+        .collect();
+    for (plant, bloom_periods) in plants {
+        for period in periods.iter_mut().filter(|p| bloom_periods & (1 << p.period) != 0) {
+            period.plants.push(plant.clone());
+        }
+    }
+    let gaps = periods.iter().filter(|p| p.plants.is_empty()).map(|p| p.period).collect();
+
+    Ok(BloomCalendar { periods, gaps })
+}
+
+const JOURNAL_COLUMNS: &str = "id, entry_date, text, tags, plant_id, activity_id, created_at, updated_at";
+
+fn journal_entry_from_row(row: &rusqlite::Row) -> rusqlite::Result<JournalEntry> {
+    Ok(JournalEntry {
+        id: row.get(0)?,
+        entry_date: row.get(1)?,
+        text: row.get(2)?,
+        tags: json_from_row(row, 3)?,
+        plant_id: row.get(4)?,
+        activity_id: row.get(5)?,
+        created_at: row.get(6)?,
+        updated_at: row.get(7)?,
+    })
+}
+
+fn query_journal(conn: &Connection, condition: &str, params: &[&dyn rusqlite::ToSql]) -> Result<Vec<JournalEntry>, String> {
+    conn.prepare(&format!("SELECT {} FROM journal_entries WHERE {}", JOURNAL_COLUMNS, condition))
+        .map_err(|e| e.to_string())?
+        .query_map(params, journal_entry_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+/// Check the date and text of an entry and tidy up its tags: lowercase, without a leading
+/// '#', no duplicates.
+fn normalize_journal_entry(entry: &mut JournalEntry) -> Result<(), String> {
+    chrono::NaiveDate::parse_from_str(&entry.entry_date, "%Y-%m-%d")
+        .map_err(|_| format!("Invalid date: {}", entry.entry_date))?;
+    entry.text = entry.text.trim().to_string();
+    if entry.text.is_empty() {
+        return Err("A journal entry needs some text".to_string());
+    }
+
+    let mut tags: Vec<String> = Vec::new();
+    for tag in &entry.tags {
+        let tag = tag.trim().trim_start_matches('#').trim().to_lowercase();
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    entry.tags = tags;
+    Ok(())
+}
+
+/// Which journal entries to return. All fields are optional and combined.
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+pub struct JournalFilter {
+    /// Words that must all appear in the text or tags
+    pub text: Option<String>,
+    pub tag: Option<String>,
+    pub plant_id: Option<i64>,
+    pub activity_id: Option<i64>,
+    /// Inclusive date range, YYYY-MM-DD
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+/// Journal entries matching `filter`, newest first.
+#[tauri::command]
+pub fn get_journal_entries(db: State<Database>, filter: Option<JournalFilter>) -> Result<Vec<JournalEntry>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let filter = filter.unwrap_or_default();
+
+    let mut conditions = vec!["1 = 1".to_string()];
+    let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+
+    for word in filter.text.as_deref().unwrap_or("").split_whitespace() {
+        params.push(Box::new(format!("%{}%", word)));
+        conditions.push(format!("(text LIKE ?{0} OR tags LIKE ?{0})", params.len()));
+    }
+    if let Some(tag) = filter.tag.filter(|t| !t.is_empty()) {
+        params.push(Box::new(tag.to_lowercase()));
+        conditions.push(format!("EXISTS (SELECT 1 FROM json_each(tags) WHERE value = ?{})", params.len()));
+    }
+    if let Some(plant_id) = filter.plant_id {
+        params.push(Box::new(plant_id));
+        conditions.push(format!("plant_id = ?{}", params.len()));
+    }
+    if let Some(activity_id) = filter.activity_id {
+        params.push(Box::new(activity_id));
+        conditions.push(format!("activity_id = ?{}", params.len()));
+    }
+    if let Some(from) = filter.from.filter(|d| !d.is_empty()) {
+        params.push(Box::new(from));
+        conditions.push(format!("entry_date >= ?{}", params.len()));
+    }
+    if let Some(to) = filter.to.filter(|d| !d.is_empty()) {
+        params.push(Box::new(to));
+        conditions.push(format!("entry_date <= ?{}", params.len()));
+    }
+
+    let condition = format!("{} ORDER BY entry_date DESC, id DESC", conditions.join(" AND "));
+    let params: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+    query_journal(&conn, &condition, &params)
+}
+
+#[derive(Debug, Serialize)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: i64,
+}
+
+/// All tags used in the journal with how often, most used first.
+#[tauri::command]
+pub fn get_journal_tags(db: State<Database>) -> Result<Vec<TagCount>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT tag.value, COUNT(*) FROM journal_entries, json_each(journal_entries.tags) AS tag
+             GROUP BY tag.value ORDER BY COUNT(*) DESC, tag.value",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let tags = stmt
+        .query_map([], |row| Ok(TagCount { tag: row.get(0)?, count: row.get(1)? }))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(tags)
+}
+
+#[tauri::command]
+pub fn create_journal_entry(db: State<Database>, mut entry: JournalEntry) -> Result<JournalEntry, String> {
+    normalize_journal_entry(&mut entry)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "INSERT INTO journal_entries (entry_date, text, tags, plant_id, activity_id) VALUES (?1, ?2, ?3, ?4, ?5)",
+        rusqlite::params![
+            entry.entry_date,
+            entry.text,
+            serde_json::to_string(&entry.tags).map_err(|e| e.to_string())?,
+            entry.plant_id,
+            entry.activity_id,
+        ],
+    ).map_err(|e| e.to_string())?;
+
+    let id = conn.last_insert_rowid();
+
+    Ok(JournalEntry {
+        id: Some(id),
+        ..entry
+    })
+}
+
+#[tauri::command]
+pub fn update_journal_entry(db: State<Database>, mut entry: JournalEntry) -> Result<(), String> {
+    normalize_journal_entry(&mut entry)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "UPDATE journal_entries SET entry_date = ?1, text = ?2, tags = ?3, plant_id = ?4, activity_id = ?5, updated_at = CURRENT_TIMESTAMP WHERE id = ?6",
+        rusqlite::params![
+            entry.entry_date,
+            entry.text,
+            serde_json::to_string(&entry.tags).map_err(|e| e.to_string())?,
+            entry.plant_id,
+            entry.activity_id,
+            entry.id,
+        ],
+    ).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn delete_journal_entry(db: State<Database>, id: i64) -> Result<(), String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    let original_files = photo_original_files(&conn, "journal_entry_id = ?1", id)?;
+
+    conn.execute("DELETE FROM journal_entries WHERE id = ?1", [id])
+        .map_err(|e| e.to_string())?;
+
+    remove_unused_originals(&conn, &db.get_path(), &original_files).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+const DEFAULT_SEARCH_LIMIT: usize = 50;
+
+#[tauri::command]
+pub fn search(db: State<Database>, query: String, limit: Option<usize>) -> Result<Vec<SearchResult>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    crate::search::search(&conn, &query, limit.unwrap_or(DEFAULT_SEARCH_LIMIT)).map_err(|e| e.to_string())
+}
+
+const PHOTO_COLUMNS: &str = "id, plant_id, activity_id, journal_entry_id, plant_task_id, sort_order, thumbnail, original_file, caption, taken_at, is_primary, created_at";
+
+fn photo_from_row(row: &rusqlite::Row, db_path: &Path) -> rusqlite::Result<PlantPhoto> {
+    let thumbnail_blob: Option<Vec<u8>> = row.get(6)?;
+    let original_file: Option<String> = row.get(7)?;
+
+    Ok(PlantPhoto {
+        id: row.get(0)?,
+        plant_id: row.get(1)?,
+        activity_id: row.get(2)?,
+        journal_entry_id: row.get(3)?,
+        plant_task_id: row.get(4)?,
+        sort_order: row.get(5)?,
+        thumbnail: thumbnail_blob.map(|b| STANDARD.encode(&b)),
+        has_original: original_file.is_some_and(|f| original_path(db_path, &f).exists()),
+        caption: row.get(8)?,
+        taken_at: row.get(9)?,
+        is_primary: row.get(10)?,
+        created_at: row.get(11)?,
+    })
+}
+
+/// The owner's photos matching `condition`, which can use the owner's id as ?1 and `params`
+/// from ?2 on.
+fn query_photos(
+    db: &Database,
+    owner: PhotoOwner,
+    condition: &str,
+    params: &[&dyn rusqlite::ToSql],
+    order_by: &str,
+) -> Result<Vec<PlantPhoto>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    backfill_photos(&conn, owner).map_err(|e| e.to_string())?;
+
+    let db_path = db.get_path();
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM plant_photos WHERE {} = ?1 AND {} ORDER BY {}",
+            PHOTO_COLUMNS,
+            owner.column(),
+            condition,
+            order_by
+        ))
+        .map_err(|e| e.to_string())?;
+
+    let owner_id = owner.id();
+    let mut all_params: Vec<&dyn rusqlite::ToSql> = vec![&owner_id];
+    all_params.extend_from_slice(params);
+    let photos = stmt
+        .query_map(all_params.as_slice(), |row| photo_from_row(row, &db_path))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(photos)
+}
+
+/// Photos without a date count from when they were added
+const PHOTO_DATE: &str = "COALESCE(taken_at, created_at)";
+/// How far from the same date last year a photo may have been taken to count as "a year ago"
+const YEAR_AGO_WINDOW_DAYS: i64 = 14;
+
+/// The owner's photos in the order the user arranged them.
+#[tauri::command]
+pub fn get_photos(db: State<Database>, owner: PhotoOwner) -> Result<Vec<PlantPhoto>, String> {
+    query_photos(&db, owner, "1", &[], "sort_order")
+}
+
+/// The owner's photos by the date they were taken, oldest first.
+#[tauri::command]
+pub fn get_photo_timeline(db: State<Database>, owner: PhotoOwner) -> Result<Vec<PlantPhoto>, String> {
+    query_photos(&db, owner, "1", &[], &format!("{}, id", PHOTO_DATE))
+}
+
+/// The owner's photos from around the same date a year before `date` (YYYY-MM-DD, today when
+/// left out), closest to that date first. Shows how a plant looked this time last year.
+#[tauri::command]
+pub fn get_photos_a_year_ago(db: State<Database>, owner: PhotoOwner, date: Option<String>) -> Result<Vec<PlantPhoto>, String> {
+    let date = match date {
+        Some(date) => chrono::NaiveDate::parse_from_str(&date, "%Y-%m-%d").map_err(|_| format!("Invalid date: {}", date))?,
+        None => Local::now().date_naive(),
+    };
+    let year_ago = date
+        .with_year(date.year() - 1)
+        // February 29th
+        .or_else(|| date.pred_opt()?.with_year(date.year() - 1))
+        .ok_or_else(|| format!("Invalid date: {}", date))?
+        .format("%Y-%m-%d")
+        .to_string();
+    query_photos(
+        &db,
+        owner,
+        &format!("abs(julianday(date({})) - julianday(?2)) <= ?3", PHOTO_DATE),
+        &[&year_ago, &YEAR_AGO_WINDOW_DAYS],
+        &format!("abs(julianday(date({})) - julianday(?2)), id", PHOTO_DATE),
+    )
+}
+
+#[derive(Debug, Serialize)]
+pub struct PhotoImage {
+    pub mime_type: String,
+    pub data: String, // base64 encoded
+}
+
+/// Full-size photo for the enlarged view. With `original` the file as it came from the camera
+/// is returned when it was kept, otherwise the stored (downscaled) image.
+#[tauri::command]
+pub fn get_photo_image(db: State<Database>, id: i64, original: Option<bool>) -> Result<PhotoImage, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    let (image_data, original_file): (Option<Vec<u8>>, Option<String>) = conn
+        .query_row(
+            "SELECT image_data, original_file FROM plant_photos WHERE id = ?1",
+            [id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| e.to_string())?;
+
+    let original_bytes = original_file
+        .filter(|_| original.unwrap_or(false))
+        .and_then(|f| std::fs::read(original_path(&db.get_path(), &f)).ok());
+    let bytes = original_bytes
+        .or(image_data)
+        .ok_or_else(|| "This photo has no image".to_string())?;
+
+    Ok(PhotoImage {
+        mime_type: mime_type(&bytes).to_string(),
+        data: STANDARD.encode(&bytes),
+    })
+}
+
+/// Store a processed photo, and its original file when the garden keeps originals.
+fn insert_photo(
+    db: &Database,
+    owner: PhotoOwner,
+    sort_order: i32,
+    bytes: &[u8],
+    photo: ProcessedPhoto,
+    keep_originals: bool,
+) -> Result<PlantPhoto, String> {
+    let taken_at = exif_taken_at(bytes);
+    let original_file = if keep_originals {
+        Some(store_original(&db.get_path(), &strip_metadata(bytes)?)?)
+    } else {
+        None
+    };
+
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    let inserted = conn.execute(
+        &format!(
+            "INSERT INTO plant_photos ({}, sort_order, image_data, thumbnail, original_file, taken_at, content_hash, perceptual_hash)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            owner.column()
+        ),
+        rusqlite::params![
+            owner.id(),
+            sort_order,
+            photo.image,
+            photo.thumbnail,
+            original_file,
+            taken_at,
+            content_hash(bytes),
+            photo.perceptual_hash,
+        ],
+    );
+    if let Err(e) = inserted {
+        // Don't leave behind an original nothing refers to, e.g. when the owner was just deleted
+        if let Some(file) = original_file {
+            remove_unused_originals(&conn, &db.get_path(), &[file]).map_err(|e| e.to_string())?;
+        }
+        return Err(e.to_string());
+    }
+
+    let id = conn.last_insert_rowid();
+
+    let owner_id = |matches: bool| Some(owner.id()).filter(|_| matches);
+
+    Ok(PlantPhoto {
+        id: Some(id),
+        plant_id: owner_id(matches!(owner, PhotoOwner::Plant(_))),
+        activity_id: owner_id(matches!(owner, PhotoOwner::Activity(_))),
+        journal_entry_id: owner_id(matches!(owner, PhotoOwner::JournalEntry(_))),
+        plant_task_id: owner_id(matches!(owner, PhotoOwner::PlantTask(_))),
+        sort_order,
+        thumbnail: Some(STANDARD.encode(&photo.thumbnail)),
+        has_original: original_file.is_some(),
+        caption: None,
+        taken_at,
+        is_primary: false,
+        created_at: None,
+    })
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub fn add_photo(app: AppHandle, db: State<Database>, owner: PhotoOwner, image_data: String, sort_order: i32) -> Result<PlantPhoto, String> {
+    let image_bytes = STANDARD.decode(&image_data).map_err(|e| e.to_string())?;
+
+    // Decode and resize before taking the lock, this is the slow part
+    let photo = process_photo(&image_bytes)?;
+    let keep_originals = load_config(&app).active().photo_settings.keep_originals;
+
+    insert_photo(&db, owner, sort_order, &image_bytes, photo, keep_originals)
+}
+
+#[derive(Debug, Serialize, Default)]
+pub struct PhotoImportReport {
+    pub imported: Vec<PlantPhoto>,
+    /// Files skipped because the plant already has the same or a very similar photo
+    pub duplicates: Vec<String>,
+    /// Files that couldn't be imported, with the reason
+    pub failed: Vec<String>,
+}
+
+/// Add image files from disk to a plant or activity, skipping exact and near duplicates of
+/// its photos and of each other.
+#[tauri::command]
+pub fn import_photos(app: AppHandle, db: State<Database>, owner: PhotoOwner, paths: Vec<String>) -> Result<PhotoImportReport, String> {
+    let keep_originals = load_config(&app).active().photo_settings.keep_originals;
+
+    let (mut sort_order, mut known) = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        backfill_photos(&conn, owner).map_err(|e| e.to_string())?;
+
+        let sort_order: i32 = conn
+            .query_row(
+                &format!("SELECT COALESCE(MAX(sort_order), 0) FROM plant_photos WHERE {} = ?1", owner.column()),
+                [owner.id()],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        let known = conn
+            .prepare(&format!("SELECT content_hash, perceptual_hash FROM plant_photos WHERE {} = ?1", owner.column()))
+            .map_err(|e| e.to_string())?
+            .query_map([owner.id()], |row| Ok((row.get::<_, Option<String>>(0)?, row.get::<_, Option<i64>>(1)?)))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        (sort_order, known)
+    };
+
+    let mut report = PhotoImportReport::default();
+    for path in paths {
+        let name = Path::new(&path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.clone());
+
+        let bytes = match std::fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                report.failed.push(format!("{}: {}", name, e));
+                continue;
+            }
+        };
+
+        let hash = content_hash(&bytes);
+        if known.iter().any(|(known_hash, _)| known_hash.as_deref() == Some(hash.as_str())) {
+            report.duplicates.push(name);
+            continue;
+        }
+
+        let photo = match process_photo(&bytes) {
+            Ok(photo) => photo,
+            Err(e) => {
+                report.failed.push(format!("{}: {}", name, e));
+                continue;
+            }
+        };
+        if known.iter().any(|(_, known_hash)| known_hash.is_some_and(|h| is_near_duplicate(h, photo.perceptual_hash))) {
+            report.duplicates.push(name);
+            continue;
+        }
+
+        sort_order += 1;
+        known.push((Some(hash), Some(photo.perceptual_hash)));
+        match insert_photo(&db, owner, sort_order, &bytes, photo, keep_originals) {
+            Ok(inserted) => report.imported.push(inserted),
+            Err(e) => report.failed.push(format!("{}: {}", name, e)),
+        }
+    }
+
+    Ok(report)
+}
+
+/// Store the new order of a plant's or activity's photos. `photo_ids` must list all of its photos.
+#[tauri::command(rename_all = "camelCase")]
+pub fn reorder_photos(db: State<Database>, owner: PhotoOwner, photo_ids: Vec<i64>) -> Result<(), String> {
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+    let count: usize = tx
+        .query_row(
+            &format!("SELECT COUNT(*) FROM plant_photos WHERE {} = ?1", owner.column()),
+            [owner.id()],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if count != photo_ids.len() {
+        return Err("The photos changed, reload and try again".to_string());
+    }
+
+    for (index, id) in photo_ids.iter().enumerate() {
+        let updated = tx
+            .execute(
+                &format!("UPDATE plant_photos SET sort_order = ?1 WHERE id = ?2 AND {} = ?3", owner.column()),
+                rusqlite::params![index as i32 + 1, id, owner.id()],
+            )
+            .map_err(|e| e.to_string())?;
+        if updated == 0 {
+            return Err(format!("Photo {} belongs to something else", id));
+        }
+    }
+
+    tx.commit().map_err(|e| e.to_string())
+}
+
+/// Check that a photo date is a date or a date and time, as the gallery sorts on it.
+fn check_taken_at(taken_at: &str) -> Result<(), String> {
+    let is_date = chrono::NaiveDate::parse_from_str(taken_at, "%Y-%m-%d").is_ok();
+    if is_date || chrono::NaiveDateTime::parse_from_str(taken_at, "%Y-%m-%d %H:%M:%S").is_ok() {
+        Ok(())
+    } else {
+        Err(format!("Invalid date: {}, expected year-month-day like 2026-04-15", taken_at))
+    }
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub fn update_photo(db: State<Database>, id: i64, caption: Option<String>, taken_at: Option<String>) -> Result<(), String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    let caption = caption.map(|c| c.trim().to_string()).filter(|c| !c.is_empty());
+    let taken_at = taken_at.map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+    if let Some(taken_at) = &taken_at {
+        check_taken_at(taken_at)?;
+    }
+
+    let updated = conn.execute(
+        "UPDATE plant_photos SET caption = ?1, taken_at = ?2 WHERE id = ?3",
+        rusqlite::params![caption, taken_at, id],
+    ).map_err(|e| e.to_string())?;
+    if updated == 0 {
+        return Err("The photo no longer exists".to_string());
+    }
+
+    Ok(())
+}
+
+/// Make `id` the primary photo of its owner, or clear the owner's primary photo when `id` is None.
+#[tauri::command]
+pub fn set_primary_photo(db: State<Database>, owner: PhotoOwner, id: Option<i64>) -> Result<(), String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    if let Some(id) = id {
+        let belongs: bool = conn
+            .query_row(
+                &format!("SELECT EXISTS(SELECT 1 FROM plant_photos WHERE id = ?1 AND {} = ?2)", owner.column()),
+                rusqlite::params![id, owner.id()],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        if !belongs {
+            return Err(format!("Photo {} belongs to something else", id));
+        }
+    }
+
+    conn.execute(
+        &format!(
+            "UPDATE plant_photos SET is_primary = (id IS ?1) WHERE {} = ?2 AND is_primary != (id IS ?1)",
+            owner.column()
+        ),
+        rusqlite::params![id, owner.id()],
+    ).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Original files of the photos matching `condition` with `id` as ?1, to clean up after
+/// deleting them.
+fn photo_original_files(conn: &Connection, condition: &str, id: i64) -> Result<Vec<String>, String> {
+    conn.prepare(&format!("SELECT original_file FROM plant_photos WHERE ({}) AND original_file IS NOT NULL", condition))
+        .map_err(|e| e.to_string())?
+        .query_map([id], |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_photo(db: State<Database>, id: i64) -> Result<(), String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    let original_files = photo_original_files(&conn, "id = ?1", id)?;
+
+    conn.execute("DELETE FROM plant_photos WHERE id = ?1", [id])
+        .map_err(|e| e.to_string())?;
+
+    remove_unused_originals(&conn, &db.get_path(), &original_files).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_photo_settings(app: AppHandle) -> PhotoSettings {
+    load_config(&app).active().photo_settings.clone()
+}
+
+#[tauri::command]
+pub fn save_photo_settings(app: AppHandle, settings: PhotoSettings) -> Result<(), String> {
+    let mut config = load_config(&app);
+    config.active_mut().photo_settings = settings;
+    save_config(&app, &config).map_err(|e| format!("Failed to save config: {}", e))
+}
+
+#[tauri::command]
+pub fn get_location(app: AppHandle) -> Location {
+    load_config(&app).active().location.clone()
+}
+
+/// Save the garden's location and return the shift it gives plant periods, in half-months.
+#[tauri::command]
+pub fn save_location(app: AppHandle, mut location: Location) -> Result<i32, String> {
+    let text = |value: Option<String>| value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+    location.last_frost = text(location.last_frost);
+    location.first_frost = text(location.first_frost);
+    location.check()?;
+    let shift = location.period_shift()?;
+
+    let mut config = load_config(&app);
+    config.active_mut().location = location;
+    save_config(&app, &config).map_err(|e| format!("Failed to save config: {}", e))?;
+    Ok(shift)
+}
+
+/// Import plants from a tab-separated sheet. With `split_varieties`, plants named
+/// "Species - Variety" become varieties of that species, which is created if needed.
+#[tauri::command(rename_all = "camelCase")]
+pub fn import_plants_tsv(db: State<Database>, tsv_content: String, split_varieties: Option<bool>) -> Result<u32, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let split_varieties = split_varieties.unwrap_or(false);
+    // Species created for varieties earlier in the sheet, by lowercase name. When the species has
+    // a row of its own further down, that row fills it in.
+    let mut created_species: HashMap<String, i64> = HashMap::new();
+
+    let lines: Vec<&str> = tsv_content.lines().collect();
+    if lines.is_empty() {
+        return Ok(0);
+    }
+
+    let mut imported = 0;
+
+    // Skip header row (line 0)
+    for line in lines.iter().skip(1) {
+        let cols: Vec<&str> = line.split('\t').collect();
+        if cols.is_empty() || cols[0].trim().is_empty() {
+            continue;
+        }
+
+        let name = cols[0].trim().to_string();
+
+        let mut sow_periods: i32 = 0;
+        let mut plant_periods: i32 = 0;
+
+        for month_idx in 0..12 {
+            let early_col = 1 + month_idx * 2;
+            let late_col = 2 + month_idx * 2;
+
+            if early_col < cols.len() {
+                let val = cols[early_col].trim();
+                if val.contains('Z') {
+                    sow_periods |= 1 << (month_idx * 2);
+                }
+                if val.contains('P') {
+                    plant_periods |= 1 << (month_idx * 2);
+                }
+            }
+
+            if late_col < cols.len() {
+                let val = cols[late_col].trim();
+                if val.contains('Z') {
+                    sow_periods |= 1 << (month_idx * 2 + 1);
+                }
+                if val.contains('P') {
+                    plant_periods |= 1 << (month_idx * 2 + 1);
+                }
+            }
+        }
+
+        if let Some(species_id) = created_species.remove(&name.to_lowercase()) {
+            conn.execute(
+                "UPDATE plants SET sow_periods = ?1, plant_periods = ?2 WHERE id = ?3",
+                (&sow_periods, &plant_periods, &species_id),
+            ).map_err(|e| e.to_string())?;
+            imported += 1;
+            continue;
+        }
+
+        let parent_id = match split_variety_name(&name).filter(|_| split_varieties) {
+            Some((species, _)) => {
+                let (species_id, created) = find_or_create_species(&conn, species)?;
+                if created {
+                    created_species.insert(species.to_lowercase(), species_id);
+                }
+                Some(species_id)
+            }
+            None => None,
+        };
+
+        conn.execute(
+            "INSERT INTO plants (name, sow_periods, plant_periods, parent_id) VALUES (?1, ?2, ?3, ?4)",
+            (&name, &sow_periods, &plant_periods, &parent_id),
+        ).map_err(|e| e.to_string())?;
+        inherit_unset_fields(&conn, conn.last_insert_rowid())?;
+
+        imported += 1;
+    }
+
+    Ok(imported)
+}
+
+#[tauri::command]
+pub fn integrity_check(db: State<Database>) -> Result<IntegrityReport, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    check_integrity(&conn).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_startup_error(startup: State<StartupState>) -> Option<String> {
+    startup.error.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub fn retry_migrations(db: State<Database>, startup: State<StartupState>) -> Result<(), String> {
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let result = run_migrations(&mut conn);
+    *startup.error.lock().unwrap() = result.clone().err();
+    result
+}
+
+#[derive(Debug, Serialize)]
+pub struct SchemaInfo {
+    pub current_version: i32,
+    pub latest_version: i32,
+}
+
+#[tauri::command]
+pub fn get_schema_info(db: State<Database>) -> Result<SchemaInfo, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    Ok(SchemaInfo {
+        current_version: current_schema_version(&conn).map_err(|e| e.to_string())?,
+        latest_version: latest_schema_version(),
+    })
+}
+
+/// Move the schema to `version`, e.g. to roll back before reinstalling an older release.
+#[tauri::command]
+pub fn migrate_to(app: AppHandle, db: State<Database>, startup: State<StartupState>, version: i32) -> Result<(), String> {
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    // Down migrations can drop data, so take a backup first
+    backup_database(&app, &conn)?;
+
+    crate::db::migrate_to(&mut conn, version)?;
+
+    // Running below the latest version is deliberate here, not a startup failure
+    *startup.error.lock().unwrap() = None;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_backups(app: AppHandle, db: State<Database>) -> Vec<BackupInfo> {
+    crate::backup::list_backups(&get_backup_dir(&app), db.get_key().as_deref())
+}
+
+#[tauri::command]
+pub fn backup_now(app: AppHandle, db: State<Database>) -> Result<String, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let backup_path = backup_database(&app, &conn)?;
+    Ok(backup_path.file_name().unwrap().to_string_lossy().to_string())
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub fn restore_backup(app: AppHandle, db: State<Database>, file_name: String) -> Result<(), String> {
+    crate::backup::restore_backup(&app, &db, &file_name)?;
+    app.state::<SyncState>().database_replaced(&app);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_backup_retention(app: AppHandle) -> BackupRetention {
+    load_config(&app).active().backup_retention.clone()
+}
+
+#[tauri::command]
+pub fn save_backup_retention(app: AppHandle, retention: BackupRetention) -> Result<u32, String> {
+    let mut config = load_config(&app);
+    config.active_mut().backup_retention = retention;
+    save_config(&app, &config).map_err(|e| format!("Failed to save config: {}", e))?;
+
+    // Apply the new policy right away
+    Ok(crate::backup::prune_backups(&get_backup_dir(&app), &config.active().backup_retention) as u32)
+}
+
+#[tauri::command]
+pub fn get_backup_schedule(app: AppHandle) -> BackupSchedule {
+    load_config(&app).active().backup_schedule.clone()
+}
+
+#[tauri::command]
+pub fn save_backup_schedule(app: AppHandle, schedule: BackupSchedule) -> Result<(), String> {
+    let mut config = load_config(&app);
+    config.active_mut().backup_schedule = schedule;
+    save_config(&app, &config).map_err(|e| format!("Failed to save config: {}", e))
+}
+
+#[tauri::command]
+pub fn get_database_path(db: State<Database>) -> String {
+    db.get_path().to_string_lossy().to_string()
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub fn relocate_database(
+    app: AppHandle,
+    db: State<Database>,
+    new_path: String,
+    mode: RelocateMode,
+) -> Result<String, String> {
+    let old_path = db.get_path();
+    crate::db::relocate_database(&db, Path::new(&new_path), mode)?;
+    app.state::<SyncState>().database_replaced(&app);
+
+    // Save new path to config
+    let mut config = load_config(&app);
+    config.add_recent_database(Path::new(&new_path));
+    config.active_mut().database_path = Some(new_path);
+    save_config(&app, &config).map_err(|e| format!("Failed to save config: {}", e))?;
+
+    Ok(match mode {
+        RelocateMode::Move => format!(
+            "Database moved. The old file at {} was left in place and can be deleted.",
+            old_path.display()
+        ),
+        RelocateMode::Open => "Switched to the database at the new location.".to_string(),
+    })
+}
+
+#[tauri::command]
+pub fn get_recent_databases(app: AppHandle, db: State<Database>) -> Vec<String> {
+    let current = db.get_path().to_string_lossy().to_string();
+    load_config(&app)
+        .recent_databases
+        .into_iter()
+        .filter(|path| *path != current && Path::new(path).exists())
+        .collect()
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProfileInfo {
+    pub id: String,
+    pub name: String,
+    pub database_path: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProfileList {
+    pub active: String,
+    pub profiles: Vec<ProfileInfo>,
+}
+
+fn profile_info(app: &AppHandle, profile: &Profile) -> ProfileInfo {
+    ProfileInfo {
+        id: profile.id.clone(),
+        name: profile.name.clone(),
+        database_path: get_profile_db_path(app, profile).to_string_lossy().to_string(),
+    }
+}
+
+#[tauri::command]
+pub fn list_profiles(app: AppHandle) -> ProfileList {
+    let config = load_config(&app);
+    ProfileList {
+        active: config.active_profile.clone(),
+        profiles: config.profiles.iter().map(|p| profile_info(&app, p)).collect(),
+    }
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub fn create_profile(app: AppHandle, name: String, database_path: Option<String>) -> Result<ProfileInfo, String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("Give the garden a name".to_string());
+    }
+
+    let mut config = load_config(&app);
+
+    // Derive a file-name-safe id from the name, e.g. "School Garden" becomes "school-garden"
+    let slug: String = name
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect::<String>()
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    let base = if slug.is_empty() { "garden".to_string() } else { slug };
+    let mut id = base.clone();
+    let mut suffix = 2;
+    while id == DEFAULT_PROFILE_ID || config.profiles.iter().any(|p| p.id == id) {
+        id = format!("{}-{}", base, suffix);
+        suffix += 1;
+    }
+
+    let profile = Profile {
+        id,
+        name,
+        database_path,
+        ..Default::default()
+    };
+    let info = profile_info(&app, &profile);
+    config.profiles.push(profile);
+    save_config(&app, &config).map_err(|e| format!("Failed to save config: {}", e))?;
+    Ok(info)
+}
+
+#[tauri::command]
+pub fn switch_profile(app: AppHandle, db: State<Database>, id: String) -> Result<(), String> {
+    let mut config = load_config(&app);
+    let profile = config
+        .profiles
+        .iter()
+        .find(|p| p.id == id)
+        .ok_or_else(|| format!("No garden with id {}", id))?;
+    let db_path = get_profile_db_path(&app, profile);
+
+    crate::db::open_database(&db, &db_path)?;
+    app.state::<SyncState>().database_replaced(&app);
+
+    config.active_profile = id;
+    config.add_recent_database(&db_path);
+    save_config(&app, &config).map_err(|e| format!("Failed to save config: {}", e))
+}
+
+#[tauri::command]
+pub fn delete_profile(app: AppHandle, id: String) -> Result<(), String> {
+    let mut config = load_config(&app);
+    if config.active_profile == id {
+        return Err("Switch to another garden before removing this one".to_string());
+    }
+    // The database file and backups are left alone
+    config.profiles.retain(|p| p.id != id);
+    save_config(&app, &config).map_err(|e| format!("Failed to save config: {}", e))
+}
+
+#[derive(Debug, Serialize)]
+pub struct EncryptionStatus {
+    /// Whether this build of the app includes SQLCipher
+    pub available: bool,
+    pub encrypted: bool,
+}
+
+#[tauri::command]
+pub fn get_encryption_status(db: State<Database>) -> EncryptionStatus {
+    EncryptionStatus {
+        available: cfg!(feature = "encryption"),
+        encrypted: db.get_key().is_some(),
+    }
+}
+
+#[tauri::command]
+pub fn is_database_locked(startup: State<StartupState>) -> bool {
+    *startup.locked.lock().unwrap()
+}
 
-        let mut sow_periods: i32 = 0;
-        let mut plant_periods: i32 = 0;
+#[tauri::command]
+pub fn unlock_database(app: AppHandle, db: State<Database>, startup: State<StartupState>, passphrase: String) -> Result<(), String> {
+    crate::db::unlock_database(&db, &passphrase)?;
+    *startup.locked.lock().unwrap() = false;
+    crate::prepare_database(&app, true);
+    Ok(())
+}
 
-        for month_idx in 0..12 {
-            let early_col = 1 + month_idx * 2;
-            let late_col = 2 + month_idx * 2;
+fn check_passphrase(passphrase: &str) -> Result<(), String> {
+    if passphrase.chars().count() < 8 {
+        return Err("Use a passphrase of at least 8 characters".to_string());
+    }
+    Ok(())
+}
 
-            if early_col < cols.len() {
-                let val = cols[early_col].trim();
-                if val.contains('Z') {
-                    sow_periods |= 1 << (month_idx * 2);
-                }
-                if val.contains('P') {
-                    plant_periods |= 1 << (month_idx * 2);
-                }
-            }
+/// Back up the database, then encrypt, decrypt or re-key it.
+fn change_encryption(app: &AppHandle, db: &Database, new_key: Option<&str>) -> Result<(), String> {
+    {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        backup_database(app, &conn)?;
+    }
+    crate::db::set_encryption(db, new_key)?;
+    app.state::<SyncState>().database_replaced(app);
+    Ok(())
+}
 
-            if late_col < cols.len() {
-                let val = cols[late_col].trim();
-                if val.contains('Z') {
-                    sow_periods |= 1 << (month_idx * 2 + 1);
-                }
-                if val.contains('P') {
-                    plant_periods |= 1 << (month_idx * 2 + 1);
-                }
-            }
-        }
+#[tauri::command]
+pub fn encrypt_database(app: AppHandle, db: State<Database>, passphrase: String) -> Result<(), String> {
+    if db.get_key().is_some() {
+        return Err("The database is already encrypted".to_string());
+    }
+    check_passphrase(&passphrase)?;
+    change_encryption(&app, &db, Some(&passphrase))
+}
 
-        conn.execute(
-            "INSERT INTO plants (name, sow_periods, plant_periods) VALUES (?1, ?2, ?3)",
-            (&name, &sow_periods, &plant_periods),
-        ).map_err(|e| e.to_string())?;
+#[tauri::command]
+pub fn decrypt_database(app: AppHandle, db: State<Database>) -> Result<(), String> {
+    if db.get_key().is_none() {
+        return Err("The database is not encrypted".to_string());
+    }
+    change_encryption(&app, &db, None)
+}
 
-        imported += 1;
+#[tauri::command]
+pub fn change_passphrase(app: AppHandle, db: State<Database>, passphrase: String) -> Result<(), String> {
+    if db.get_key().is_none() {
+        return Err("The database is not encrypted".to_string());
     }
+    check_passphrase(&passphrase)?;
+    change_encryption(&app, &db, Some(&passphrase))
+}
 
-    Ok(imported)
+#[tauri::command]
+pub fn get_sync_status(sync: State<SyncState>) -> SyncStatus {
+    sync.status()
 }
 
 #[tauri::command]
-pub fn get_database_path(db: State<Database>) -> String {
-    db.get_path().to_string_lossy().to_string()
+pub fn resolve_external_change(
+    app: AppHandle,
+    db: State<Database>,
+    resolution: ExternalChangeResolution,
+) -> Result<Option<String>, String> {
+    crate::sync::resolve_external_change(&app, &db, resolution)
 }
 
 #[tauri::command(rename_all = "camelCase")]
-pub fn save_database_path(
-    app: AppHandle,
-    new_path: String,
-) -> Result<String, String> {
-    // Save new path to config
-    let config = AppConfig {
-        database_path: Some(new_path),
-    };
-    save_config(&app, &config).map_err(|e| format!("Failed to save config: {}", e))?;
+pub fn merge_database(app: AppHandle, db: State<Database>, other_path: String) -> Result<MergeReport, String> {
+    let other_path = Path::new(&other_path);
+    if other_path.canonicalize().ok() == db.get_path().canonicalize().ok() {
+        return Err("Can't merge the database with itself".to_string());
+    }
+
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    // Merging can delete rows, so take a backup first
+    backup_database(&app, &conn)?;
 
-    Ok("Database moved. Please restart the app to use the new location.".to_string())
+    crate::merge::merge_database(&mut conn, other_path, db.get_key().as_deref())
 }
 
+/// The plants of one category in a period, in the order the categories are configured
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CategoryData {
-    pub vegetables: Vec<String>,
-    pub flowers: Vec<String>,
-    pub herbs: Vec<String>,
-    pub other: Vec<String>,
+    pub name: String,
+    pub plants: Vec<String>,
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PrintData {
     pub month_name: String,
-    pub sow_early: CategoryData,
-    pub sow_late: CategoryData,
-    pub plant_early: CategoryData,
-    pub plant_late: CategoryData,
+    pub sow_early: Vec<CategoryData>,
+    pub sow_late: Vec<CategoryData>,
+    pub plant_early: Vec<CategoryData>,
+    pub plant_late: Vec<CategoryData>,
     pub activities: Vec<String>,
 }
 
@@ -439,10 +2551,9 @@ pub fn generate_pdf(data: PrintData) -> Result<String, String> {
     let font = doc.add_builtin_font(BuiltinFont::Helvetica).map_err(|e| e.to_string())?;
     let font_bold = doc.add_builtin_font(BuiltinFont::HelveticaBold).map_err(|e| e.to_string())?;
 
-    // Three-column layout (one per category)
-    let col1_x = Mm(10.0);   // Vegetables
-    let col2_x = Mm(75.0);   // Flowers
-    let col3_x = Mm(140.0);  // Herbs
+    // Three-column layout, one column per category; more categories continue on the next row
+    let columns = [Mm(10.0), Mm(75.0), Mm(140.0)];
+    let col1_x = columns[0];
     let line_height = Mm(2.8);
     let bottom_margin = Mm(12.0);
     let top_start = Mm(290.0);
@@ -486,13 +2597,27 @@ pub fn generate_pdf(data: PrintData) -> Result<String, String> {
         y
     };
 
-    // Helper to draw a time period (Early/Late) with 3 category columns
-    let draw_period = |layer: &PdfLayerReference, veg: &[String], flow: &[String], herb: &[String], x1: Mm, x2: Mm, x3: Mm, y: Mm| -> Mm {
-        let start_y = y;
-        let y1 = if veg.is_empty() { y } else { draw_items(layer, veg, x1, y) };
-        let y2 = if flow.is_empty() { start_y } else { draw_items(layer, flow, x2, start_y) };
-        let y3 = if herb.is_empty() { start_y } else { draw_items(layer, herb, x3, start_y) };
-        *[y1, y2, y3].iter().min_by(|a, b| a.0.partial_cmp(&b.0).unwrap()).unwrap()
+    // Helper to draw a time period (Early/Late): the categories with plants, side by side
+    let draw_period = |layer: &PdfLayerReference, categories: &[CategoryData], mut y: Mm| -> Mm {
+        let filled: Vec<&CategoryData> = categories.iter().filter(|c| !c.plants.is_empty()).collect();
+        if filled.is_empty() {
+            layer.use_text("Nothing this period", 5.5, col1_x, y, &font);
+            return y - line_height;
+        }
+        for row in filled.chunks(columns.len()) {
+            for (category, x) in row.iter().zip(columns) {
+                layer.use_text(&category.name, 6.5, x, y, &font_bold);
+            }
+            let start_y = y - Mm(3.0);
+            y = row
+                .iter()
+                .zip(columns)
+                .map(|(category, x)| draw_items(layer, &category.plants, x, start_y))
+                .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap())
+                .unwrap_or(start_y);
+            y -= Mm(1.5);
+        }
+        y
     };
 
     // === SOW THIS MONTH ===
@@ -503,12 +2628,7 @@ pub fn generate_pdf(data: PrintData) -> Result<String, String> {
     layer!().use_text(&format!("Early {}", data.month_name), 8.0, col1_x, y, &font_bold);
     y = y - Mm(3.5);
 
-    layer!().use_text("Vegetables", 6.5, col1_x, y, &font_bold);
-    layer!().use_text("Flowers", 6.5, col2_x, y, &font_bold);
-    layer!().use_text("Herbs", 6.5, col3_x, y, &font_bold);
-    y = y - Mm(3.0);
-
-    y = draw_period(&layer!(), &data.sow_early.vegetables, &data.sow_early.flowers, &data.sow_early.herbs, col1_x, col2_x, col3_x, y);
+    y = draw_period(&layer!(), &data.sow_early, y);
     y = y - Mm(3.0);
     check_page!();
 
@@ -516,12 +2636,7 @@ pub fn generate_pdf(data: PrintData) -> Result<String, String> {
     layer!().use_text(&format!("Late {}", data.month_name), 8.0, col1_x, y, &font_bold);
     y = y - Mm(3.5);
 
-    layer!().use_text("Vegetables", 6.5, col1_x, y, &font_bold);
-    layer!().use_text("Flowers", 6.5, col2_x, y, &font_bold);
-    layer!().use_text("Herbs", 6.5, col3_x, y, &font_bold);
-    y = y - Mm(3.0);
-
-    y = draw_period(&layer!(), &data.sow_late.vegetables, &data.sow_late.flowers, &data.sow_late.herbs, col1_x, col2_x, col3_x, y);
+    y = draw_period(&layer!(), &data.sow_late, y);
     y = y - Mm(5.0);
     check_page!();
 
@@ -533,12 +2648,7 @@ pub fn generate_pdf(data: PrintData) -> Result<String, String> {
     layer!().use_text(&format!("Early {}", data.month_name), 8.0, col1_x, y, &font_bold);
     y = y - Mm(3.5);
 
-    layer!().use_text("Vegetables", 6.5, col1_x, y, &font_bold);
-    layer!().use_text("Flowers", 6.5, col2_x, y, &font_bold);
-    layer!().use_text("Herbs", 6.5, col3_x, y, &font_bold);
-    y = y - Mm(3.0);
-
-    y = draw_period(&layer!(), &data.plant_early.vegetables, &data.plant_early.flowers, &data.plant_early.herbs, col1_x, col2_x, col3_x, y);
+    y = draw_period(&layer!(), &data.plant_early, y);
     y = y - Mm(3.0);
     check_page!();
 
@@ -546,12 +2656,7 @@ pub fn generate_pdf(data: PrintData) -> Result<String, String> {
     layer!().use_text(&format!("Late {}", data.month_name), 8.0, col1_x, y, &font_bold);
     y = y - Mm(3.5);
 
-    layer!().use_text("Vegetables", 6.5, col1_x, y, &font_bold);
-    layer!().use_text("Flowers", 6.5, col2_x, y, &font_bold);
-    layer!().use_text("Herbs", 6.5, col3_x, y, &font_bold);
-    y = y - Mm(3.0);
-
-    y = draw_period(&layer!(), &data.plant_late.vegetables, &data.plant_late.flowers, &data.plant_late.herbs, col1_x, col2_x, col3_x, y);
+    y = draw_period(&layer!(), &data.plant_late, y);
     y = y - Mm(5.0);
     check_page!();
 
@@ -574,11 +2679,713 @@ pub fn generate_pdf(data: PrintData) -> Result<String, String> {
     let file = File::create(&pdf_path).map_err(|e| format!("Failed to create PDF file: {}", e))?;
     doc.save(&mut BufWriter::new(file)).map_err(|e| format!("Failed to save PDF: {}", e))?;
 
-    // Open the PDF
+    open_pdf(&pdf_path)?;
+
+    Ok(pdf_path.to_string_lossy().to_string())
+}
+
+fn open_pdf(pdf_path: &Path) -> Result<(), String> {
     Command::new("open")
         .arg(pdf_path.to_str().unwrap())
         .spawn()
         .map_err(|e| format!("Failed to open PDF: {}", e))?;
+    Ok(())
+}
+
+/// Number of photos printed on a plant sheet when the caller doesn't specify one.
+const DEFAULT_SHEET_PHOTOS: usize = 4;
+
+const MONTH_INITIALS: [&str; 12] = ["J", "F", "M", "A", "M", "J", "J", "A", "S", "O", "N", "D"];
+
+fn sun_labels(sun_requirements: i32) -> String {
+    let mut labels = Vec::new();
+    if sun_requirements & 1 != 0 {
+        labels.push("Full Sun");
+    }
+    if sun_requirements & 2 != 0 {
+        labels.push("Partial Shade");
+    }
+    if sun_requirements & 4 != 0 {
+        labels.push("Full Shade");
+    }
+    if labels.is_empty() {
+        "-".to_string()
+    } else {
+        labels.join(", ")
+    }
+}
+
+fn format_cm(value: f64) -> String {
+    format!("{} cm", (value * 10.0).round() / 10.0)
+}
+
+/// Label and value of each botanical detail that is filled in, for the plant sheet.
+fn botanical_details(plant: &Plant) -> Vec<(&'static str, String)> {
+    let capitalize = |value: &str| {
+        let mut chars = value.chars();
+        chars
+            .next()
+            .map(|first| first.to_uppercase().chain(chars).collect())
+            .unwrap_or_default()
+    };
+    let text = |value: &Option<String>| value.as_deref().map(str::trim).filter(|v| !v.is_empty()).map(capitalize);
+
+    [
+        ("Family", text(&plant.family)),
+        ("Lifecycle", text(&plant.lifecycle)),
+        ("Hardiness", text(&plant.hardiness_zone)),
+        ("Water", text(&plant.water_needs)),
+        ("Flowers", text(&plant.flower_color)),
+        ("Established", plant.established_year.map(|year| year.to_string())),
+        ("Sowing depth", plant.sowing_depth_cm.map(format_cm)),
+        ("In the row", plant.in_row_spacing_cm.map(format_cm)),
+        ("Between rows", plant.row_spacing_cm.map(format_cm)),
+        ("Height", plant.mature_height_cm.map(format_cm)),
+        ("Spread", plant.mature_spread_cm.map(format_cm)),
+    ]
+    .into_iter()
+    .filter_map(|(label, value)| value.map(|value| (label, value)))
+    .collect()
+}
+
+/// Word-wrap text into lines of at most `max_chars` characters, keeping existing line breaks.
+fn wrap_text(text: &str, max_chars: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut current = String::new();
+        for word in paragraph.split_whitespace() {
+            let needed = if current.is_empty() { word.chars().count() } else { current.chars().count() + 1 + word.chars().count() };
+            if needed > max_chars && !current.is_empty() {
+                lines.push(std::mem::take(&mut current));
+            }
+            if !current.is_empty() {
+                current.push(' ');
+            }
+            current.push_str(word);
+        }
+        lines.push(current);
+    }
+    lines
+}
+
+fn rgb(r: f32, g: f32, b: f32) -> Color {
+    Color::Rgb(Rgb::new(r, g, b, None))
+}
+
+/// Draw a labelled row of 24 half-month cells, filling the ones set in `periods`.
+fn draw_period_bar(layer: &PdfLayerReference, font: &IndirectFontRef, label: &str, periods: i32, fill: Color, x: Mm, y: Mm) {
+    let cell_width = 6.5;
+    let cell_height = 4.0;
+    let bar_x = x.0 + 18.0;
+
+    layer.use_text(label, 8.0, x, Mm(y.0 + 1.0), font);
+
+    for bit in 0..24 {
+        let llx = bar_x + bit as f32 * cell_width;
+        let rect = Rect::new(Mm(llx), y, Mm(llx + cell_width), Mm(y.0 + cell_height));
+        if periods & (1 << bit) != 0 {
+            layer.set_fill_color(fill.clone());
+            layer.add_rect(rect);
+        }
+        layer.set_outline_color(rgb(0.7, 0.7, 0.7));
+        layer.set_outline_thickness(0.3);
+        layer.add_rect(rect.with_mode(PaintMode::Stroke));
+    }
+    layer.set_fill_color(rgb(0.0, 0.0, 0.0));
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub fn generate_plant_sheet_pdf(app: AppHandle, db: State<Database>, plant_ids: Vec<i64>, max_photos: Option<usize>) -> Result<String, String> {
+    if plant_ids.is_empty() {
+        return Err("No plants selected".to_string());
+    }
+    let max_photos = max_photos.unwrap_or(DEFAULT_SHEET_PHOTOS);
+    let shift = period_shift(&app)?;
+
+    // Load plants and their photos up front so the lock isn't held while rendering
+    let mut sheets: Vec<(Plant, Vec<image_crate::DynamicImage>)> = Vec::new();
+    let category_names: HashMap<String, String>;
+    {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+        category_names = query_categories(&conn)?.into_iter().map(|c| (c.key, c.name)).collect();
+
+        let mut plant_stmt = conn
+            .prepare(&format!("SELECT {} FROM plants WHERE id = ?1", PLANT_COLUMNS))
+            .map_err(|e| e.to_string())?;
+        let mut photo_stmt = conn
+            .prepare("SELECT image_data FROM plant_photos WHERE plant_id = ?1 ORDER BY is_primary DESC, sort_order LIMIT ?2")
+            .map_err(|e| e.to_string())?;
+
+        for id in &plant_ids {
+            let mut plant = plant_stmt
+                .query_row([id], plant_from_row)
+                .map_err(|e| e.to_string())?;
+            shift_plant(&mut plant, shift);
+
+            let blobs = photo_stmt
+                .query_map(rusqlite::params![id, max_photos as i64], |row| row.get::<_, Option<Vec<u8>>>(0))
+                .map_err(|e| e.to_string())?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?;
+
+            // Skip photos that can't be decoded rather than failing the whole sheet
+            let images = blobs
+                .into_iter()
+                .flatten()
+                .filter_map(|bytes| image_crate::load_from_memory(&bytes).ok())
+                .map(|img| image_crate::DynamicImage::ImageRgb8(img.thumbnail(1000, 1000).to_rgb8()))
+                .collect();
+
+            sheets.push((plant, images));
+        }
+    }
+
+    let file_stem = if sheets.len() == 1 {
+        let name: String = sheets[0].0.name
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+            .collect();
+        format!("plant-sheet-{}", name)
+    } else {
+        "plant-sheets".to_string()
+    };
+    let pdf_path = std::env::temp_dir().join(format!("{}.pdf", file_stem));
+
+    let (doc, first_page, first_layer) = PdfDocument::new("Plant Sheets", Mm(210.0), Mm(297.0), "Layer 1");
+
+    let font = doc.add_builtin_font(BuiltinFont::Helvetica).map_err(|e| e.to_string())?;
+    let font_bold = doc.add_builtin_font(BuiltinFont::HelveticaBold).map_err(|e| e.to_string())?;
+    let font_italic = doc.add_builtin_font(BuiltinFont::HelveticaOblique).map_err(|e| e.to_string())?;
+
+    let left = Mm(15.0);
+    let top_start = Mm(280.0);
+    let bottom_margin = Mm(15.0);
+    let photo_box_w = 85.0;
+    let photo_box_h = 65.0;
+
+    for (index, (plant, images)) in sheets.into_iter().enumerate() {
+        // One plant per page
+        let (page, layer_idx) = if index == 0 {
+            (first_page, first_layer)
+        } else {
+            doc.add_page(Mm(210.0), Mm(297.0), "Layer 1")
+        };
+        let mut layer = doc.get_page(page).get_layer(layer_idx);
+        let mut y = top_start;
+
+        let title = match plant.cultivar.as_deref().map(str::trim).filter(|c| !c.is_empty()) {
+            Some(cultivar) => format!("{} '{}'", plant.name, cultivar),
+            None => plant.name.clone(),
+        };
+        layer.use_text(&title, 18.0, left, y, &font_bold);
+        y -= Mm(8.0);
+
+        if let Some(scientific_name) = plant.scientific_name.as_deref().map(str::trim).filter(|n| !n.is_empty()) {
+            layer.use_text(scientific_name, 11.0, left, Mm(y.0 + 2.0), &font_italic);
+            y -= Mm(6.0);
+        }
+
+        layer.use_text("Type:", 9.0, left, y, &font_bold);
+        let category = plant.plant_type.as_ref().and_then(|key| category_names.get(key));
+        layer.use_text(category.map(String::as_str).unwrap_or("Other"), 9.0, Mm(left.0 + 12.0), y, &font);
+        layer.use_text("Sun:", 9.0, Mm(left.0 + 60.0), y, &font_bold);
+        layer.use_text(sun_labels(plant.sun_requirements), 9.0, Mm(left.0 + 70.0), y, &font);
+        y -= Mm(6.0);
+
+        // Botanical details, three to a row
+        let details = botanical_details(&plant);
+        for row in details.chunks(3) {
+            for (column, (label, value)) in row.iter().enumerate() {
+                let x = left.0 + column as f32 * 60.0;
+                layer.use_text(format!("{}:", label), 9.0, Mm(x), y, &font_bold);
+                layer.use_text(value, 9.0, Mm(x + 24.0), y, &font);
+            }
+            y -= Mm(5.0);
+        }
+        y -= Mm(4.0);
+
+        // Month header above the period bars (two cells per month)
+        for (month, initial) in MONTH_INITIALS.iter().enumerate() {
+            let x = left.0 + 18.0 + month as f32 * 13.0 + 5.0;
+            layer.use_text(*initial, 7.0, Mm(x), y, &font_bold);
+        }
+        y -= Mm(6.0);
+
+        draw_period_bar(&layer, &font, "Sow", plant.sow_periods, rgb(0.30, 0.69, 0.31), left, y);
+        y -= Mm(6.0);
+        draw_period_bar(&layer, &font, "Plant", plant.plant_periods, rgb(0.63, 0.40, 0.20), left, y);
+        y -= Mm(6.0);
+        if plant.bloom_periods != 0 {
+            draw_period_bar(&layer, &font, "Bloom", plant.bloom_periods, rgb(0.91, 0.45, 0.65), left, y);
+            y -= Mm(6.0);
+        }
+        y -= Mm(4.0);
+
+        if let Some(notes) = plant.notes.as_deref().filter(|n| !n.trim().is_empty()) {
+            layer.use_text("Notes", 11.0, left, y, &font_bold);
+            y -= Mm(5.5);
+            for line in wrap_text(notes, 95) {
+                if y < bottom_margin {
+                    let (page, layer_idx) = doc.add_page(Mm(210.0), Mm(297.0), "Layer 1");
+                    layer = doc.get_page(page).get_layer(layer_idx);
+                    y = top_start;
+                }
+                layer.use_text(line, 9.0, left, y, &font);
+                y -= Mm(4.2);
+            }
+            y -= Mm(5.0);
+        }
+
+        if !images.is_empty() {
+            layer.use_text("Photos", 11.0, left, y, &font_bold);
+            y -= Mm(3.0);
+
+            for (photo_idx, image) in images.iter().enumerate() {
+                let column = photo_idx % 2;
+                if column == 0 {
+                    if y.0 - photo_box_h < bottom_margin.0 {
+                        let (page, layer_idx) = doc.add_page(Mm(210.0), Mm(297.0), "Layer 1");
+                        layer = doc.get_page(page).get_layer(layer_idx);
+                        y = top_start;
+                    }
+                    y -= Mm(photo_box_h);
+                }
+
+                // Pick a DPI so the image fits its box while keeping the aspect ratio
+                let dpi_w = image.width() as f32 / (photo_box_w / 25.4);
+                let dpi_h = image.height() as f32 / (photo_box_h / 25.4);
+                let dpi = dpi_w.max(dpi_h);
+
+                let x = left.0 + column as f32 * (photo_box_w + 5.0);
+                Image::from_dynamic_image(image).add_to_layer(
+                    layer.clone(),
+                    ImageTransform {
+                        translate_x: Some(Mm(x)),
+                        translate_y: Some(y),
+                        dpi: Some(dpi),
+                        ..Default::default()
+                    },
+                );
+
+                if column == 1 {
+                    y -= Mm(5.0);
+                }
+            }
+        }
+    }
+
+    let file = File::create(&pdf_path).map_err(|e| format!("Failed to create PDF file: {}", e))?;
+    doc.save(&mut BufWriter::new(file)).map_err(|e| format!("Failed to save PDF: {}", e))?;
+
+    open_pdf(&pdf_path)?;
+
+    Ok(pdf_path.to_string_lossy().to_string())
+}
+
+const MONTH_NAMES: [&str; 12] = [
+    "January", "February", "March", "April", "May", "June",
+    "July", "August", "September", "October", "November", "December",
+];
+
+/// Human readable name of a half-month period bit, e.g. "Early March".
+fn period_label(bit: u32) -> String {
+    let half = if bit & 1 == 0 { "Early" } else { "Late" };
+    format!("{} {}", half, MONTH_NAMES[(bit / 2) as usize])
+}
+
+/// The half-month period (0..24) we're currently in.
+fn current_period() -> u32 {
+    let today = Local::now();
+    today.month0() * 2 + if today.day() > 15 { 1 } else { 0 }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ShoppingListItem {
+    pub plant_id: i64,
+    pub name: String,
+    pub packets_needed: i32,
+    pub last_price: Option<f64>,
+    /// Next sowing period, e.g. "Early March"; None when the plant has no sowing periods
+    pub sow_by: Option<String>,
+    /// Half-month periods until `sow_by`, 0 meaning it can be sown right now
+    pub periods_until_sowing: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SupplierGroup {
+    pub supplier_id: Option<i64>,
+    pub supplier_name: String,
+    pub items: Vec<ShoppingListItem>,
+    pub estimated_total: f64,
+}
+
+/// Build the reorder list grouped by supplier, most urgent items (and groups) first.
+fn build_shopping_list(conn: &Connection, shift: i32) -> Result<Vec<SupplierGroup>, String> {
+    let now = current_period();
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT p.id, p.name, p.packets_needed, p.last_price, p.sow_periods, p.supplier_id, s.name
+             FROM plants p LEFT JOIN suppliers s ON s.id = p.supplier_id
+             WHERE p.needs_reorder = 1",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let rows = stmt
+        .query_map([], |row| {
+            let sow_periods = shift_periods(row.get(4)?, shift);
+            let periods_until_sowing = (0..24u32).find(|offset| sow_periods & (1 << ((now + offset) % 24)) != 0);
+            let item = ShoppingListItem {
+                plant_id: row.get(0)?,
+                name: row.get(1)?,
+                packets_needed: row.get::<_, Option<i32>>(2)?.unwrap_or(1),
+                last_price: row.get(3)?,
+                sow_by: periods_until_sowing.map(|offset| period_label((now + offset) % 24)),
+                periods_until_sowing,
+            };
+            Ok((row.get::<_, Option<i64>>(5)?, row.get::<_, Option<String>>(6)?, item))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut groups: BTreeMap<Option<i64>, SupplierGroup> = BTreeMap::new();
+    for (supplier_id, supplier_name, item) in rows {
+        let group = groups.entry(supplier_id).or_insert_with(|| SupplierGroup {
+            supplier_id,
+            supplier_name: supplier_name.unwrap_or_else(|| "No supplier".to_string()),
+            items: Vec::new(),
+            estimated_total: 0.0,
+        });
+        group.estimated_total += item.last_price.unwrap_or(0.0) * item.packets_needed as f64;
+        group.items.push(item);
+    }
+
+    let urgency = |item: &ShoppingListItem| item.periods_until_sowing.unwrap_or(u32::MAX);
+
+    let mut groups: Vec<SupplierGroup> = groups.into_values().collect();
+    for group in &mut groups {
+        group.items.sort_by(|a, b| urgency(a).cmp(&urgency(b)).then_with(|| a.name.cmp(&b.name)));
+    }
+    groups.sort_by(|a, b| {
+        let a_first = a.items.first().map(urgency).unwrap_or(u32::MAX);
+        let b_first = b.items.first().map(urgency).unwrap_or(u32::MAX);
+        a_first.cmp(&b_first).then_with(|| a.supplier_name.cmp(&b.supplier_name))
+    });
+
+    Ok(groups)
+}
+
+fn format_price(price: Option<f64>) -> String {
+    price.map(|p| format!("€{:.2}", p)).unwrap_or_default()
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[tauri::command]
+pub fn get_shopping_list(app: AppHandle, db: State<Database>) -> Result<Vec<SupplierGroup>, String> {
+    let shift = period_shift(&app)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    build_shopping_list(&conn, shift)
+}
+
+/// Plain text version of the shopping list, meant for pasting into an order email.
+#[tauri::command]
+pub fn get_shopping_list_text(app: AppHandle, db: State<Database>) -> Result<String, String> {
+    let shift = period_shift(&app)?;
+    let groups = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        build_shopping_list(&conn, shift)?
+    };
+
+    let mut text = format!("Seed order - {}\n", Local::now().format("%d-%m-%Y"));
+    for group in &groups {
+        text.push_str(&format!("\n{}\n", group.supplier_name));
+        for item in &group.items {
+            let mut details = Vec::new();
+            if let Some(sow_by) = &item.sow_by {
+                details.push(format!("sow {}", sow_by.to_lowercase()));
+            }
+            if item.last_price.is_some() {
+                details.push(format!("last paid {}", format_price(item.last_price)));
+            }
+            text.push_str(&format!("- {}x {}", item.packets_needed, item.name));
+            if !details.is_empty() {
+                text.push_str(&format!(" ({})", details.join(", ")));
+            }
+            text.push('\n');
+        }
+    }
+
+    Ok(text)
+}
+
+#[tauri::command]
+pub fn export_shopping_list_csv(app: AppHandle, db: State<Database>, path: String) -> Result<(), String> {
+    let shift = period_shift(&app)?;
+    let groups = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        build_shopping_list(&conn, shift)?
+    };
+
+    let mut csv = String::from("Supplier,Plant,Packets,Last price,Sow by\n");
+    for group in &groups {
+        for item in &group.items {
+            csv.push_str(&format!(
+                "{},{},{},{},{}\n",
+                csv_field(&group.supplier_name),
+                csv_field(&item.name),
+                item.packets_needed,
+                item.last_price.map(|p| format!("{:.2}", p)).unwrap_or_default(),
+                item.sow_by.as_deref().unwrap_or(""),
+            ));
+        }
+    }
+
+    std::fs::write(&path, csv).map_err(|e| format!("Failed to write CSV: {}", e))
+}
+
+#[tauri::command]
+pub fn generate_shopping_list_pdf(app: AppHandle, db: State<Database>) -> Result<String, String> {
+    let shift = period_shift(&app)?;
+    let groups = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        build_shopping_list(&conn, shift)?
+    };
+
+    let pdf_path = std::env::temp_dir().join("seed-shopping-list.pdf");
+
+    let (doc, page1, layer1) = PdfDocument::new("Seed Shopping List", Mm(210.0), Mm(297.0), "Layer 1");
+
+    let font = doc.add_builtin_font(BuiltinFont::Helvetica).map_err(|e| e.to_string())?;
+    let font_bold = doc.add_builtin_font(BuiltinFont::HelveticaBold).map_err(|e| e.to_string())?;
+
+    let name_x = Mm(15.0);
+    let packets_x = Mm(120.0);
+    let price_x = Mm(140.0);
+    let sow_x = Mm(162.0);
+    let line_height = Mm(5.0);
+    let bottom_margin = Mm(15.0);
+    let top_start = Mm(280.0);
+
+    let mut layer = doc.get_page(page1).get_layer(layer1);
+    let mut y = top_start;
+
+    layer.use_text("Seed Shopping List", 16.0, name_x, y, &font_bold);
+    layer.use_text(Local::now().format("%d-%m-%Y").to_string(), 9.0, sow_x, y, &font);
+    y -= Mm(10.0);
+
+    if groups.is_empty() {
+        layer.use_text("No seeds need to be reordered.", 10.0, name_x, y, &font);
+    }
+
+    for group in &groups {
+        // Keep a supplier header together with at least its first item
+        if y.0 - 3.0 * line_height.0 < bottom_margin.0 {
+            let (page, layer_idx) = doc.add_page(Mm(210.0), Mm(297.0), "Layer 1");
+            layer = doc.get_page(page).get_layer(layer_idx);
+            y = top_start;
+        }
+
+        layer.use_text(&group.supplier_name, 12.0, name_x, y, &font_bold);
+        if group.estimated_total > 0.0 {
+            layer.use_text(format!("est. {}", format_price(Some(group.estimated_total))), 9.0, sow_x, y, &font);
+        }
+        y -= line_height;
+
+        layer.use_text("Plant", 8.0, name_x, y, &font_bold);
+        layer.use_text("Packets", 8.0, packets_x, y, &font_bold);
+        layer.use_text("Last price", 8.0, price_x, y, &font_bold);
+        layer.use_text("Sow by", 8.0, sow_x, y, &font_bold);
+        y -= line_height;
+
+        for item in &group.items {
+            if y < bottom_margin {
+                let (page, layer_idx) = doc.add_page(Mm(210.0), Mm(297.0), "Layer 1");
+                layer = doc.get_page(page).get_layer(layer_idx);
+                y = top_start;
+            }
+            layer.use_text(&item.name, 9.0, name_x, y, &font);
+            layer.use_text(item.packets_needed.to_string(), 9.0, packets_x, y, &font);
+            layer.use_text(format_price(item.last_price), 9.0, price_x, y, &font);
+            layer.use_text(item.sow_by.as_deref().unwrap_or("-"), 9.0, sow_x, y, &font);
+            y -= line_height;
+        }
+        y -= Mm(4.0);
+    }
+
+    let file = File::create(&pdf_path).map_err(|e| format!("Failed to create PDF file: {}", e))?;
+    doc.save(&mut BufWriter::new(file)).map_err(|e| format!("Failed to save PDF: {}", e))?;
+
+    open_pdf(&pdf_path)?;
 
     Ok(pdf_path.to_string_lossy().to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::open_connection;
+    use std::path::PathBuf;
+
+    #[test]
+    fn corrupt_tag_lists_are_errors() {
+        let conn = Connection::open_in_memory().unwrap();
+        let tags = |json: &str| conn.query_row("SELECT ?1", [json], |row| json_from_row::<Vec<i64>>(row, 0));
+        assert_eq!(tags("[3,1]").unwrap(), [3, 1]);
+        assert!(tags("[3,").is_err());
+        assert!(tags("[\"kas\"]").is_err());
+    }
+
+    #[test]
+    fn corrupt_journal_tags_are_errors() {
+        let conn = Connection::open_in_memory().unwrap();
+        let entry = |tags: &str| {
+            conn.query_row("SELECT 1, '2026-04-01', 'First frost', ?1, NULL, NULL, NULL, NULL", [tags], journal_entry_from_row)
+        };
+        assert_eq!(entry(r#"["frost"]"#).unwrap().tags, ["frost"]);
+        assert!(entry(r#"["frost""#).is_err());
+    }
+
+    #[test]
+    fn corrupt_smart_list_filters_are_errors() {
+        let conn = Connection::open_in_memory().unwrap();
+        let list = |filter: &str| {
+            conn.query_row("SELECT 1, 'Shade herbs', ?1, NULL, NULL", [filter], smart_list_from_row)
+        };
+        let filter = list(r#"{"plant_types": ["herb"], "sow_periods": 16, "future_field": true}"#).unwrap().filter;
+        assert_eq!((filter.plant_types, filter.sow_periods), (vec!["herb".to_string()], 16));
+        assert!(list(r#"{"plant_types": ["herb""#).is_err());
+    }
+
+    #[test]
+    fn failed_photo_inserts_leave_no_original_behind() {
+        let dir = std::env::temp_dir().join(format!("tuinapp-photo-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = Database::new(&dir.join("garden.db")).unwrap();
+        run_migrations(&mut db.conn.lock().unwrap()).unwrap();
+        db.conn.lock().unwrap().execute("INSERT INTO plants (id, name) VALUES (1, 'Tomato')", []).unwrap();
+
+        let mut bytes = Vec::new();
+        ::image::DynamicImage::ImageRgb8(::image::RgbImage::new(16, 16))
+            .write_to(&mut std::io::Cursor::new(&mut bytes), ::image::ImageFormat::Jpeg)
+            .unwrap();
+        // Original files sit in a folder per first two characters of their name
+        let originals = |db: &Database| -> usize {
+            match std::fs::read_dir(crate::photos::originals_dir(&db.get_path())) {
+                Ok(folders) => folders.flatten().map(|folder| std::fs::read_dir(folder.path()).unwrap().count()).sum(),
+                Err(_) => 0,
+            }
+        };
+
+        // The plant it belongs to doesn't exist
+        assert!(insert_photo(&db, PhotoOwner::Plant(2), 0, &bytes, process_photo(&bytes).unwrap(), true).is_err());
+        assert_eq!(originals(&db), 0);
+
+        let photo = insert_photo(&db, PhotoOwner::Plant(1), 0, &bytes, process_photo(&bytes).unwrap(), true).unwrap();
+        assert!(photo.has_original);
+        // An original another photo uses stays
+        assert!(insert_photo(&db, PhotoOwner::Plant(2), 0, &bytes, process_photo(&bytes).unwrap(), true).is_err());
+        assert_eq!(originals(&db), 1);
+
+        drop(db);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn photo_dates_need_a_day() {
+        assert!(check_taken_at("2026-04-15").is_ok());
+        assert!(check_taken_at("2026-04-15 09:30:00").is_ok());
+        assert!(check_taken_at("last spring").is_err());
+        assert!(check_taken_at("2026-02-30").is_err());
+        assert!(check_taken_at("15-04-2026").is_err());
+    }
+
+    fn new_database() -> Connection {
+        let mut conn = open_connection(&PathBuf::from(":memory:"), None).unwrap();
+        run_migrations(&mut conn).unwrap();
+        conn
+    }
+
+    fn fields(fields: &[&str]) -> Vec<String> {
+        fields.iter().map(|field| field.to_string()).collect()
+    }
+
+    #[test]
+    fn varieties_follow_only_the_fields_they_inherit() {
+        let conn = new_database();
+        conn.execute_batch(
+            "INSERT INTO plants (id, name, plant_type, sow_periods, family) VALUES (1, 'Tomato', 'vegetable_fruit', 6, 'Solanaceae');
+             INSERT INTO plants (id, name, parent_id, sow_periods) VALUES (2, 'Tomato - Roma', 1, 6);",
+        )
+        .unwrap();
+        set_inherited_fields(&conn, 2, &fields(&["plant_type", "family", "bloom_periods"])).unwrap();
+        assert_eq!(load_plant(&conn, 2).unwrap().family.as_deref(), Some("Solanaceae"));
+
+        conn.execute(
+            "UPDATE plants SET plant_type = 'herb', sow_periods = 24, family = NULL, bloom_periods = 48, sun_requirements = 1 WHERE id = 1",
+            [],
+        )
+        .unwrap();
+
+        // The sowing periods only happened to be equal, and no sun is a value of its own
+        let variety = load_plant(&conn, 2).unwrap();
+        assert_eq!(variety.plant_type.as_deref(), Some("herb"));
+        assert_eq!(variety.family, None);
+        assert_eq!(variety.bloom_periods, 48);
+        assert_eq!(variety.sow_periods, 6);
+        assert_eq!(variety.sun_requirements, 0);
+        assert_eq!(variety.inherited_fields, Some(fields(&["plant_type", "family", "bloom_periods"])));
+    }
+
+    #[test]
+    fn new_varieties_inherit_what_they_leave_empty() {
+        let conn = new_database();
+        conn.execute_batch(
+            "INSERT INTO plants (id, name, plant_type, sow_periods, plant_periods) VALUES (1, 'Tomato', 'vegetable_fruit', 6, 24);
+             INSERT INTO plants (id, name, parent_id, sow_periods) VALUES (2, 'Tomato - Roma', 1, 3);
+             INSERT INTO plants (id, name, sow_periods) VALUES (3, 'Basil', 0);",
+        )
+        .unwrap();
+        inherit_unset_fields(&conn, 2).unwrap();
+        inherit_unset_fields(&conn, 3).unwrap();
+
+        let variety = load_plant(&conn, 2).unwrap();
+        let inherited = variety.inherited_fields.unwrap();
+        assert!(inherited.contains(&"plant_type".to_string()));
+        assert!(inherited.contains(&"plant_periods".to_string()));
+        assert!(!inherited.contains(&"sow_periods".to_string()));
+        assert_eq!((variety.sow_periods, variety.plant_periods), (3, 24));
+        assert_eq!(load_plant(&conn, 3).unwrap().inherited_fields, Some(Vec::new()));
+    }
+
+    #[test]
+    fn only_shared_fields_can_be_inherited() {
+        let conn = new_database();
+        conn.execute_batch(
+            "INSERT INTO plants (id, name) VALUES (1, 'Tomato');
+             INSERT INTO plants (id, name, parent_id) VALUES (2, 'Tomato - Roma', 1);",
+        )
+        .unwrap();
+        assert!(set_inherited_fields(&conn, 2, &fields(&["name"])).is_err());
+        assert!(set_inherited_fields(&conn, 2, &fields(&["plant_type; DROP TABLE plants"])).is_err());
+    }
+
+    #[test]
+    fn splits_species_and_variety_names() {
+        assert_eq!(split_variety_name("Aardbei - Gele alpenaardbei"), Some(("Aardbei", "Gele alpenaardbei")));
+        assert_eq!(split_variety_name(" Tomaat  -  Roma "), Some(("Tomaat", "Roma")));
+        assert_eq!(split_variety_name("Bonen - Stok - Blauwe"), Some(("Bonen", "Stok - Blauwe")));
+        assert_eq!(split_variety_name("Goudsbloem"), None);
+        assert_eq!(split_variety_name("Zonnebloem-Reus"), None);
+        assert_eq!(split_variety_name(" - Roma"), None);
+        assert_eq!(split_variety_name("Tomaat - "), None);
+    }
+}
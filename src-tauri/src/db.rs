@@ -1,31 +1,371 @@
-use chrono::Local;
+use crate::location::Location;
+use crate::photos::{copy_originals, PhotoSettings};
 use rusqlite::{Connection, Result};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 
 pub struct Database {
     pub conn: Mutex<Connection>,
     pub path: Mutex<PathBuf>,
+    /// Passphrase of an encrypted database. Only kept in memory.
+    pub key: Mutex<Option<String>>,
+}
+
+/// Open a connection with the settings every connection to a tuinapp database needs.
+pub fn open_connection(path: &PathBuf, key: Option<&str>) -> Result<Connection> {
+    let conn = Connection::open(path)?;
+    if let Some(key) = key {
+        // Must come before anything reads the file
+        conn.pragma_update(None, "key", key)?;
+    }
+    // SQLite leaves foreign keys off per connection, which silently disables ON DELETE CASCADE
+    conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+    Ok(conn)
+}
+
+/// Whether the connection can read the database: false for an encrypted database opened
+/// without the right passphrase, or a file that isn't a database at all.
+pub fn is_readable(conn: &Connection) -> bool {
+    conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |_| Ok(())).is_ok()
+}
+
+/// Open a database that may or may not be encrypted: with `key` first, then without.
+/// Returns the connection and the key that worked.
+pub fn open_readable(path: &Path, key: Option<&str>) -> std::result::Result<(Connection, Option<String>), String> {
+    let path = path.to_path_buf();
+    let mut attempts = vec![key];
+    if key.is_some() {
+        attempts.push(None);
+    }
+    for attempt in attempts {
+        let conn = open_connection(&path, attempt).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        if is_readable(&conn) {
+            return Ok((conn, attempt.map(str::to_string)));
+        }
+    }
+    Err(format!(
+        "{} is encrypted with a different passphrase, or is not a database",
+        path.display()
+    ))
+}
+
+/// Copy the database behind `conn` to `destination` with SQLite's online backup API. The copy
+/// is encrypted with `key`, which must match the source: SQLCipher can't back up between
+/// databases with different keys.
+pub fn backup_to(conn: &Connection, destination: &Path, key: Option<&str>) -> Result<()> {
+    let mut target = Connection::open(destination)?;
+    if let Some(key) = key {
+        target.pragma_update(None, "key", key)?;
+    }
+    let backup = rusqlite::backup::Backup::new(conn, &mut target)?;
+    backup.run_to_completion(100, std::time::Duration::ZERO, None)
 }
 
 impl Database {
     pub fn new(path: &PathBuf) -> Result<Self> {
-        let conn = Connection::open(path)?;
+        let conn = open_connection(path, None)?;
         Ok(Self {
             conn: Mutex::new(conn),
             path: Mutex::new(path.clone()),
+            key: Mutex::new(None),
         })
     }
 
     pub fn get_path(&self) -> PathBuf {
         self.path.lock().unwrap().clone()
     }
+
+    pub fn get_key(&self) -> Option<String> {
+        self.key.lock().unwrap().clone()
+    }
+}
+
+/// What to do with the database file at a new location.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RelocateMode {
+    /// Copy the current database to the new path and switch to the copy
+    Move,
+    /// Switch to a database that already exists at the new path
+    Open,
+}
+
+fn plant_count(conn: &Connection) -> Result<i64> {
+    conn.query_row("SELECT COUNT(*) FROM plants", [], |row| row.get(0))
+}
+
+/// Copy the open database to `new_path` and check the copy is complete. The copy goes through
+/// SQLite's online backup API into a temporary file that is renamed into place, so a reader at
+/// the new location never sees a half-written database.
+fn copy_database(conn: &Connection, new_path: &Path, key: Option<&str>) -> std::result::Result<(), String> {
+    if new_path.exists() {
+        return Err(format!(
+            "{} already exists. Use \"Open Existing Database\" to switch to it instead.",
+            new_path.display()
+        ));
+    }
+
+    // Fold any WAL contents into the main file first; a no-op in rollback journal mode
+    conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE);")
+        .map_err(|e| format!("Failed to checkpoint database: {}", e))?;
+
+    let partial_path = new_path.with_extension("db.partial");
+    backup_to(conn, &partial_path, key).map_err(|e| format!("Failed to copy database: {}", e))?;
+
+    let verify = || -> std::result::Result<(), String> {
+        let copy = open_connection(&partial_path, key).map_err(|e| e.to_string())?;
+        let integrity: String = copy
+            .query_row("PRAGMA integrity_check", [], |row| row.get(0))
+            .map_err(|e| e.to_string())?;
+        if integrity != "ok" {
+            return Err(format!("copy failed the integrity check: {}", integrity));
+        }
+        let expected = plant_count(conn).map_err(|e| e.to_string())?;
+        let copied = plant_count(&copy).map_err(|e| e.to_string())?;
+        if copied != expected {
+            return Err(format!("copy has {} plants instead of {}", copied, expected));
+        }
+        Ok(())
+    };
+
+    let result = verify()
+        .and_then(|_| std::fs::rename(&partial_path, new_path).map_err(|e| e.to_string()))
+        .map_err(|e| format!("Failed to copy database: {}", e));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&partial_path);
+    }
+    result
+}
+
+/// Point the running app at a database at `new_path`, either by moving the current database
+/// there or by opening the one already there. The open connection is only replaced once the
+/// new database has opened and migrated successfully.
+pub fn relocate_database(db: &Database, new_path: &Path, mode: RelocateMode) -> std::result::Result<(), String> {
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    if new_path == db.get_path() {
+        return Err("That is already the current database".to_string());
+    }
+
+    match mode {
+        RelocateMode::Move => {
+            copy_database(&conn, new_path, db.get_key().as_deref())?;
+            copy_originals(&db.get_path(), new_path)?;
+        }
+        RelocateMode::Open => {
+            if !new_path.exists() {
+                return Err(format!("No database found at {}", new_path.display()));
+            }
+        }
+    }
+
+    swap_database(db, &mut conn, new_path)
+}
+
+/// Switch the running app to the database at `path`, creating it if needed.
+pub fn open_database(db: &Database, path: &Path) -> std::result::Result<(), String> {
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    swap_database(db, &mut conn, path)
+}
+
+/// Open the database at `path`, bring it up to date and make it the app's database. The
+/// current connection is only replaced once that succeeded. An encrypted database can only
+/// be opened if it uses the same passphrase as the current one.
+fn swap_database(db: &Database, conn: &mut Connection, path: &Path) -> std::result::Result<(), String> {
+    let (mut new_conn, key) = open_readable(path, db.get_key().as_deref())?;
+    run_migrations(&mut new_conn)?;
+
+    *conn = new_conn;
+    *db.path.lock().unwrap() = path.to_path_buf();
+    *db.key.lock().unwrap() = key;
+    Ok(())
+}
+
+/// Encrypt the database with `new_key`, change its passphrase, or decrypt it when `new_key`
+/// is None. The database is exported with `sqlcipher_export` to a new file next to it, which
+/// is checked and then renamed over the original.
+pub fn set_encryption(db: &Database, new_key: Option<&str>) -> std::result::Result<(), String> {
+    if !cfg!(feature = "encryption") {
+        return Err("This version of the app was built without encryption support".to_string());
+    }
+
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let db_path = db.get_path();
+    let staging_path = db_path.with_extension("db.encrypting");
+    let _ = std::fs::remove_file(&staging_path);
+
+    // An empty key attaches the target unencrypted
+    conn.execute(
+        "ATTACH DATABASE ?1 AS target KEY ?2",
+        rusqlite::params![staging_path.to_string_lossy(), new_key.unwrap_or("")],
+    )
+    .map_err(|e| format!("Failed to create encrypted copy: {}", e))?;
+    let exported = conn.query_row("SELECT sqlcipher_export('target')", [], |_| Ok(()));
+    let _ = conn.execute("DETACH DATABASE target", []);
+
+    let checked = exported.map_err(|e| format!("Failed to export database: {}", e)).and_then(|_| {
+        let copy = open_connection(&staging_path, new_key).map_err(|e| e.to_string())?;
+        if plant_count(&copy).ok() != plant_count(&conn).ok() {
+            return Err("The exported database doesn't match the original".to_string());
+        }
+        Ok(())
+    });
+    if let Err(e) = checked {
+        let _ = std::fs::remove_file(&staging_path);
+        return Err(e);
+    }
+
+    // Close the current connection before replacing the file underneath it
+    let placeholder = Connection::open_in_memory().map_err(|e| e.to_string())?;
+    drop(std::mem::replace(&mut *conn, placeholder));
+
+    let old_key = db.get_key();
+    let swap = std::fs::rename(&staging_path, &db_path).map_err(|e| format!("Failed to replace database: {}", e));
+    let key = if swap.is_ok() { new_key.map(str::to_string) } else { old_key };
+
+    *conn = open_connection(&db_path, key.as_deref()).map_err(|e| format!("Failed to reopen database: {}", e))?;
+    *db.key.lock().unwrap() = key;
+
+    let _ = std::fs::remove_file(&staging_path);
+    swap
 }
 
+/// Unlock an encrypted database found at startup, then run the backup and migrations that
+/// were skipped while it was locked.
+pub fn unlock_database(db: &Database, passphrase: &str) -> std::result::Result<(), String> {
+    let db_path = db.get_path();
+    let new_conn = open_connection(&db_path, Some(passphrase)).map_err(|e| e.to_string())?;
+    if !is_readable(&new_conn) {
+        return Err("Wrong passphrase".to_string());
+    }
+
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    *conn = new_conn;
+    *db.key.lock().unwrap() = Some(passphrase.to_string());
+    Ok(())
+}
+
+pub const DEFAULT_PROFILE_ID: &str = "default";
+const MAX_RECENT_DATABASES: usize = 10;
+
 #[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
 pub struct AppConfig {
+    pub profiles: Vec<Profile>,
+    pub active_profile: String,
+    /// Databases opened recently, most recent first
+    pub recent_databases: Vec<String>,
+    // Settings from before profiles existed, moved into the default profile on load
+    #[serde(skip_serializing)]
+    database_path: Option<String>,
+    #[serde(skip_serializing)]
+    backup_retention: Option<BackupRetention>,
+    #[serde(skip_serializing)]
+    backup_schedule: Option<BackupSchedule>,
+}
+
+/// A garden with its own database and settings.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Profile {
+    pub id: String,
+    pub name: String,
+    /// None keeps the database in the app data folder
     pub database_path: Option<String>,
+    pub backup_retention: BackupRetention,
+    pub backup_schedule: BackupSchedule,
+    pub photo_settings: PhotoSettings,
+    pub location: Location,
+}
+
+impl AppConfig {
+    /// Make sure there is an active profile, turning the settings of a config written before
+    /// profiles existed into the default profile.
+    fn with_profiles(mut self) -> Self {
+        if self.profiles.is_empty() {
+            self.profiles.push(Profile {
+                id: DEFAULT_PROFILE_ID.to_string(),
+                name: "My Garden".to_string(),
+                database_path: self.database_path.take(),
+                backup_retention: self.backup_retention.take().unwrap_or_default(),
+                backup_schedule: self.backup_schedule.take().unwrap_or_default(),
+                ..Default::default()
+            });
+        }
+        if !self.profiles.iter().any(|p| p.id == self.active_profile) {
+            self.active_profile = self.profiles[0].id.clone();
+        }
+        self
+    }
+
+    pub fn active(&self) -> &Profile {
+        self.profiles
+            .iter()
+            .find(|p| p.id == self.active_profile)
+            .expect("config always has an active profile")
+    }
+
+    pub fn active_mut(&mut self) -> &mut Profile {
+        let active = self.active_profile.clone();
+        self.profiles
+            .iter_mut()
+            .find(|p| p.id == active)
+            .expect("config always has an active profile")
+    }
+
+    pub fn add_recent_database(&mut self, path: &Path) {
+        let path = path.to_string_lossy().to_string();
+        self.recent_databases.retain(|p| *p != path);
+        self.recent_databases.insert(0, path);
+        self.recent_databases.truncate(MAX_RECENT_DATABASES);
+    }
+}
+
+/// When to take backups while the app is running, on top of the one at startup.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct BackupSchedule {
+    /// Back up this often if anything changed since the last backup. 0 disables it.
+    pub interval_minutes: u32,
+    /// Back up as soon as this many rows have changed since the last backup. 0 disables it.
+    pub after_changes: u32,
+    /// Store backups gzip-compressed
+    pub compress: bool,
+}
+
+impl Default for BackupSchedule {
+    fn default() -> Self {
+        Self {
+            interval_minutes: 60,
+            after_changes: 500,
+            compress: false,
+        }
+    }
+}
+
+/// How many backups to keep. A backup survives if any of the rules selects it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct BackupRetention {
+    /// Most recent backups, regardless of age
+    pub keep_last: usize,
+    /// Newest backup of each of the most recent days
+    pub keep_daily: usize,
+    /// Newest backup of each of the most recent weeks
+    pub keep_weekly: usize,
+    /// Newest backup of each of the most recent months
+    pub keep_monthly: usize,
+}
+
+impl Default for BackupRetention {
+    fn default() -> Self {
+        Self {
+            keep_last: 10,
+            keep_daily: 7,
+            keep_weekly: 4,
+            keep_monthly: 12,
+        }
+    }
 }
 
 pub fn get_config_path(app: &tauri::AppHandle) -> PathBuf {
@@ -42,12 +382,12 @@ pub fn load_config(app: &tauri::AppHandle) -> AppConfig {
     let config_path = get_config_path(app);
     if config_path.exists() {
         if let Ok(content) = std::fs::read_to_string(&config_path) {
-            if let Ok(config) = serde_json::from_str(&content) {
-                return config;
+            if let Ok(config) = serde_json::from_str::<AppConfig>(&content) {
+                return config.with_profiles();
             }
         }
     }
-    AppConfig::default()
+    AppConfig::default().with_profiles()
 }
 
 pub fn save_config(app: &tauri::AppHandle, config: &AppConfig) -> std::io::Result<()> {
@@ -160,6 +500,905 @@ const MIGRATIONS: &[(i32, &str, &str)] = &[
         DROP TABLE plants;
         ALTER TABLE plants_new RENAME TO plants;"
     ),
+    // Version 4: Suppliers, plus per-plant supplier and packet info for the shopping list
+    (4,
+        "CREATE TABLE IF NOT EXISTS suppliers (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            website TEXT,
+            email TEXT,
+            notes TEXT,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+        ALTER TABLE plants ADD COLUMN supplier_id INTEGER REFERENCES suppliers(id) ON DELETE SET NULL;
+        ALTER TABLE plants ADD COLUMN packets_needed INTEGER DEFAULT 1;
+        ALTER TABLE plants ADD COLUMN last_price REAL;",
+        // Down migration: remove the columns by recreating the table, then drop suppliers
+        "CREATE TABLE plants_new (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            plant_type TEXT CHECK(plant_type IN ('vegetable_fruit', 'flower', 'herb')),
+            sun_requirements INTEGER DEFAULT 0,
+            sow_periods INTEGER DEFAULT 0,
+            plant_periods INTEGER DEFAULT 0,
+            notes TEXT,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            needs_reorder INTEGER DEFAULT 0
+        );
+        INSERT INTO plants_new SELECT
+            id, name, plant_type, sun_requirements, sow_periods, plant_periods, notes, created_at, updated_at, needs_reorder
+        FROM plants;
+        DROP TABLE plants;
+        ALTER TABLE plants_new RENAME TO plants;
+        DROP TABLE suppliers;"
+    ),
+    // Version 5: Remove rows orphaned while foreign keys weren't enforced
+    (5,
+        "DELETE FROM plant_photos WHERE plant_id NOT IN (SELECT id FROM plants);
+        UPDATE plants SET supplier_id = NULL
+            WHERE supplier_id IS NOT NULL AND supplier_id NOT IN (SELECT id FROM suppliers);",
+        // Down migration: nothing to undo, the removed rows were unreachable
+        "SELECT 1;"
+    ),
+    // Version 6: Change journal for merging databases: stable row UUIDs, modified timestamps
+    // kept current by triggers, and tombstones for deleted rows
+    (6,
+        "ALTER TABLE suppliers ADD COLUMN uuid TEXT;
+        ALTER TABLE plants ADD COLUMN uuid TEXT;
+        ALTER TABLE activities ADD COLUMN uuid TEXT;
+        ALTER TABLE plant_photos ADD COLUMN uuid TEXT;
+        UPDATE suppliers SET uuid = lower(hex(randomblob(16)));
+        CREATE UNIQUE INDEX idx_suppliers_uuid ON suppliers(uuid);
+        UPDATE plants SET uuid = lower(hex(randomblob(16)));
+        CREATE UNIQUE INDEX idx_plants_uuid ON plants(uuid);
+        UPDATE activities SET uuid = lower(hex(randomblob(16)));
+        CREATE UNIQUE INDEX idx_activities_uuid ON activities(uuid);
+        UPDATE plant_photos SET uuid = lower(hex(randomblob(16)));
+        CREATE UNIQUE INDEX idx_plant_photos_uuid ON plant_photos(uuid);
+        CREATE TABLE deleted_rows (
+            table_name TEXT NOT NULL,
+            uuid TEXT NOT NULL,
+            deleted_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (table_name, uuid)
+        );
+        CREATE TRIGGER suppliers_assign_uuid AFTER INSERT ON suppliers WHEN NEW.uuid IS NULL
+        BEGIN
+            UPDATE suppliers SET uuid = lower(hex(randomblob(16))) WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER suppliers_touch AFTER UPDATE ON suppliers
+            WHEN NEW.updated_at IS OLD.updated_at AND NEW.uuid IS OLD.uuid
+        BEGIN
+            UPDATE suppliers SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER suppliers_tombstone AFTER DELETE ON suppliers WHEN OLD.uuid IS NOT NULL
+        BEGIN
+            INSERT OR REPLACE INTO deleted_rows (table_name, uuid, deleted_at) VALUES ('suppliers', OLD.uuid, CURRENT_TIMESTAMP);
+        END;
+        CREATE TRIGGER plants_assign_uuid AFTER INSERT ON plants WHEN NEW.uuid IS NULL
+        BEGIN
+            UPDATE plants SET uuid = lower(hex(randomblob(16))) WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER plants_touch AFTER UPDATE ON plants
+            WHEN NEW.updated_at IS OLD.updated_at AND NEW.uuid IS OLD.uuid
+        BEGIN
+            UPDATE plants SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER plants_tombstone AFTER DELETE ON plants WHEN OLD.uuid IS NOT NULL
+        BEGIN
+            INSERT OR REPLACE INTO deleted_rows (table_name, uuid, deleted_at) VALUES ('plants', OLD.uuid, CURRENT_TIMESTAMP);
+        END;
+        CREATE TRIGGER activities_assign_uuid AFTER INSERT ON activities WHEN NEW.uuid IS NULL
+        BEGIN
+            UPDATE activities SET uuid = lower(hex(randomblob(16))) WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER activities_touch AFTER UPDATE ON activities
+            WHEN NEW.updated_at IS OLD.updated_at AND NEW.uuid IS OLD.uuid
+        BEGIN
+            UPDATE activities SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER activities_tombstone AFTER DELETE ON activities WHEN OLD.uuid IS NOT NULL
+        BEGIN
+            INSERT OR REPLACE INTO deleted_rows (table_name, uuid, deleted_at) VALUES ('activities', OLD.uuid, CURRENT_TIMESTAMP);
+        END;
+        CREATE TRIGGER plant_photos_assign_uuid AFTER INSERT ON plant_photos WHEN NEW.uuid IS NULL
+        BEGIN
+            UPDATE plant_photos SET uuid = lower(hex(randomblob(16))) WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER plant_photos_tombstone AFTER DELETE ON plant_photos WHEN OLD.uuid IS NOT NULL
+        BEGIN
+            INSERT OR REPLACE INTO deleted_rows (table_name, uuid, deleted_at) VALUES ('plant_photos', OLD.uuid, CURRENT_TIMESTAMP);
+        END;",
+        "DROP TRIGGER suppliers_assign_uuid;
+        DROP TRIGGER suppliers_touch;
+        DROP TRIGGER suppliers_tombstone;
+        DROP TRIGGER plants_assign_uuid;
+        DROP TRIGGER plants_touch;
+        DROP TRIGGER plants_tombstone;
+        DROP TRIGGER activities_assign_uuid;
+        DROP TRIGGER activities_touch;
+        DROP TRIGGER activities_tombstone;
+        DROP TRIGGER plant_photos_assign_uuid;
+        DROP TRIGGER plant_photos_tombstone;
+        DROP INDEX idx_suppliers_uuid;
+        ALTER TABLE suppliers DROP COLUMN uuid;
+        DROP INDEX idx_plants_uuid;
+        ALTER TABLE plants DROP COLUMN uuid;
+        DROP INDEX idx_activities_uuid;
+        ALTER TABLE activities DROP COLUMN uuid;
+        DROP INDEX idx_plant_photos_uuid;
+        ALTER TABLE plant_photos DROP COLUMN uuid;
+        DROP TABLE deleted_rows;"
+    ),
+    // Version 7: Photo thumbnails, and a reference to the original file when it is kept
+    // outside the database. Thumbnails for existing photos are generated when first loaded.
+    (7,
+        "ALTER TABLE plant_photos ADD COLUMN thumbnail BLOB;
+        ALTER TABLE plant_photos ADD COLUMN original_file TEXT;",
+        "ALTER TABLE plant_photos DROP COLUMN original_file;
+        ALTER TABLE plant_photos DROP COLUMN thumbnail;"
+    ),
+    // Version 8: Photo captions, the date a photo was taken, and a primary photo per plant
+    (8,
+        "ALTER TABLE plant_photos ADD COLUMN caption TEXT;
+        ALTER TABLE plant_photos ADD COLUMN taken_at DATETIME;
+        ALTER TABLE plant_photos ADD COLUMN is_primary INTEGER NOT NULL DEFAULT 0;",
+        "ALTER TABLE plant_photos DROP COLUMN is_primary;
+        ALTER TABLE plant_photos DROP COLUMN taken_at;
+        ALTER TABLE plant_photos DROP COLUMN caption;"
+    ),
+    // Version 9: Hashes to skip duplicate photos on import: SHA-256 of the imported file and a
+    // perceptual hash of the image
+    (9,
+        "ALTER TABLE plant_photos ADD COLUMN content_hash TEXT;
+        ALTER TABLE plant_photos ADD COLUMN perceptual_hash INTEGER;",
+        "ALTER TABLE plant_photos DROP COLUMN perceptual_hash;
+        ALTER TABLE plant_photos DROP COLUMN content_hash;"
+    ),
+    // Version 10: Photos can belong to an activity instead of a plant. Exactly one of
+    // plant_id and activity_id is set.
+    (10,
+        "CREATE TABLE plant_photos_new (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            plant_id INTEGER,
+            activity_id INTEGER,
+            sort_order INTEGER DEFAULT 0,
+            image_data BLOB,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            uuid TEXT,
+            thumbnail BLOB,
+            original_file TEXT,
+            caption TEXT,
+            taken_at DATETIME,
+            is_primary INTEGER NOT NULL DEFAULT 0,
+            content_hash TEXT,
+            perceptual_hash INTEGER,
+            CHECK ((plant_id IS NOT NULL) + (activity_id IS NOT NULL) = 1),
+            FOREIGN KEY (plant_id) REFERENCES plants(id) ON DELETE CASCADE,
+            FOREIGN KEY (activity_id) REFERENCES activities(id) ON DELETE CASCADE
+        );
+        INSERT INTO plant_photos_new (
+            id, plant_id, sort_order, image_data, created_at, uuid, thumbnail, original_file, caption, taken_at, is_primary, content_hash, perceptual_hash
+        )
+        SELECT id, plant_id, sort_order, image_data, created_at, uuid, thumbnail, original_file, caption, taken_at, is_primary, content_hash, perceptual_hash
+        FROM plant_photos;
+        DROP TABLE plant_photos;
+        ALTER TABLE plant_photos_new RENAME TO plant_photos;
+        CREATE INDEX idx_plant_photos_plant_id ON plant_photos(plant_id);
+        CREATE INDEX idx_plant_photos_activity_id ON plant_photos(activity_id);
+        CREATE UNIQUE INDEX idx_plant_photos_uuid ON plant_photos(uuid);
+        CREATE TRIGGER plant_photos_assign_uuid AFTER INSERT ON plant_photos WHEN NEW.uuid IS NULL
+        BEGIN
+            UPDATE plant_photos SET uuid = lower(hex(randomblob(16))) WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER plant_photos_tombstone AFTER DELETE ON plant_photos WHEN OLD.uuid IS NOT NULL
+        BEGIN
+            INSERT OR REPLACE INTO deleted_rows (table_name, uuid, deleted_at) VALUES ('plant_photos', OLD.uuid, CURRENT_TIMESTAMP);
+        END;",
+        // Down migration: photos of activities have nowhere to go and are dropped
+        "CREATE TABLE plant_photos_new (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            plant_id INTEGER NOT NULL,
+            sort_order INTEGER DEFAULT 0,
+            image_data BLOB,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            uuid TEXT,
+            thumbnail BLOB,
+            original_file TEXT,
+            caption TEXT,
+            taken_at DATETIME,
+            is_primary INTEGER NOT NULL DEFAULT 0,
+            content_hash TEXT,
+            perceptual_hash INTEGER,
+            FOREIGN KEY (plant_id) REFERENCES plants(id) ON DELETE CASCADE
+        );
+        INSERT INTO plant_photos_new (
+            id, plant_id, sort_order, image_data, created_at, uuid, thumbnail, original_file, caption, taken_at, is_primary, content_hash, perceptual_hash
+        )
+        SELECT id, plant_id, sort_order, image_data, created_at, uuid, thumbnail, original_file, caption, taken_at, is_primary, content_hash, perceptual_hash
+        FROM plant_photos WHERE plant_id IS NOT NULL;
+        DROP TABLE plant_photos;
+        ALTER TABLE plant_photos_new RENAME TO plant_photos;
+        CREATE UNIQUE INDEX idx_plant_photos_uuid ON plant_photos(uuid);
+        CREATE TRIGGER plant_photos_assign_uuid AFTER INSERT ON plant_photos WHEN NEW.uuid IS NULL
+        BEGIN
+            UPDATE plant_photos SET uuid = lower(hex(randomblob(16))) WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER plant_photos_tombstone AFTER DELETE ON plant_photos WHEN OLD.uuid IS NOT NULL
+        BEGIN
+            INSERT OR REPLACE INTO deleted_rows (table_name, uuid, deleted_at) VALUES ('plant_photos', OLD.uuid, CURRENT_TIMESTAMP);
+        END;"
+    ),
+    // Version 11: Garden journal. Tags are stored as a JSON array of lowercase strings.
+    (11,
+        "CREATE TABLE journal_entries (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            entry_date DATE NOT NULL,
+            text TEXT NOT NULL,
+            tags TEXT NOT NULL DEFAULT '[]',
+            plant_id INTEGER,
+            activity_id INTEGER,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            uuid TEXT,
+            FOREIGN KEY (plant_id) REFERENCES plants(id) ON DELETE SET NULL,
+            FOREIGN KEY (activity_id) REFERENCES activities(id) ON DELETE SET NULL
+        );
+        CREATE INDEX idx_journal_entries_entry_date ON journal_entries(entry_date);
+        CREATE UNIQUE INDEX idx_journal_entries_uuid ON journal_entries(uuid);
+        CREATE TRIGGER journal_entries_assign_uuid AFTER INSERT ON journal_entries WHEN NEW.uuid IS NULL
+        BEGIN
+            UPDATE journal_entries SET uuid = lower(hex(randomblob(16))) WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER journal_entries_touch AFTER UPDATE ON journal_entries
+            WHEN NEW.updated_at IS OLD.updated_at AND NEW.uuid IS OLD.uuid
+        BEGIN
+            UPDATE journal_entries SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER journal_entries_tombstone AFTER DELETE ON journal_entries WHEN OLD.uuid IS NOT NULL
+        BEGIN
+            INSERT OR REPLACE INTO deleted_rows (table_name, uuid, deleted_at) VALUES ('journal_entries', OLD.uuid, CURRENT_TIMESTAMP);
+        END;",
+        "DROP TABLE journal_entries;
+        DELETE FROM deleted_rows WHERE table_name = 'journal_entries';"
+    ),
+    // Version 12: Full-text search index over plants, activities and the journal, kept in sync
+    // by triggers. One row per entity with a title and a body column.
+    (12,
+        "CREATE VIRTUAL TABLE search_index USING fts5(
+            entity_type UNINDEXED,
+            entity_id UNINDEXED,
+            title,
+            body,
+            tokenize = 'unicode61 remove_diacritics 2'
+        );
+        INSERT INTO search_index (entity_type, entity_id, title, body)
+            SELECT 'plant', id, name, COALESCE(notes, '') FROM plants;
+        INSERT INTO search_index (entity_type, entity_id, title, body)
+            SELECT 'activity', id, name, COALESCE(description, '') FROM activities;
+        INSERT INTO search_index (entity_type, entity_id, title, body)
+            SELECT 'journal', id, entry_date, text FROM journal_entries;
+        CREATE TRIGGER plants_search_insert AFTER INSERT ON plants
+        BEGIN
+            INSERT INTO search_index (entity_type, entity_id, title, body) VALUES ('plant', NEW.id, NEW.name, COALESCE(NEW.notes, ''));
+        END;
+        CREATE TRIGGER plants_search_update AFTER UPDATE OF name, notes ON plants
+        BEGIN
+            DELETE FROM search_index WHERE entity_type = 'plant' AND entity_id = OLD.id;
+            INSERT INTO search_index (entity_type, entity_id, title, body) VALUES ('plant', NEW.id, NEW.name, COALESCE(NEW.notes, ''));
+        END;
+        CREATE TRIGGER plants_search_delete AFTER DELETE ON plants
+        BEGIN
+            DELETE FROM search_index WHERE entity_type = 'plant' AND entity_id = OLD.id;
+        END;
+        CREATE TRIGGER activities_search_insert AFTER INSERT ON activities
+        BEGIN
+            INSERT INTO search_index (entity_type, entity_id, title, body) VALUES ('activity', NEW.id, NEW.name, COALESCE(NEW.description, ''));
+        END;
+        CREATE TRIGGER activities_search_update AFTER UPDATE OF name, description ON activities
+        BEGIN
+            DELETE FROM search_index WHERE entity_type = 'activity' AND entity_id = OLD.id;
+            INSERT INTO search_index (entity_type, entity_id, title, body) VALUES ('activity', NEW.id, NEW.name, COALESCE(NEW.description, ''));
+        END;
+        CREATE TRIGGER activities_search_delete AFTER DELETE ON activities
+        BEGIN
+            DELETE FROM search_index WHERE entity_type = 'activity' AND entity_id = OLD.id;
+        END;
+        CREATE TRIGGER journal_entries_search_insert AFTER INSERT ON journal_entries
+        BEGIN
+            INSERT INTO search_index (entity_type, entity_id, title, body) VALUES ('journal', NEW.id, NEW.entry_date, NEW.text);
+        END;
+        CREATE TRIGGER journal_entries_search_update AFTER UPDATE OF entry_date, text ON journal_entries
+        BEGIN
+            DELETE FROM search_index WHERE entity_type = 'journal' AND entity_id = OLD.id;
+            INSERT INTO search_index (entity_type, entity_id, title, body) VALUES ('journal', NEW.id, NEW.entry_date, NEW.text);
+        END;
+        CREATE TRIGGER journal_entries_search_delete AFTER DELETE ON journal_entries
+        BEGIN
+            DELETE FROM search_index WHERE entity_type = 'journal' AND entity_id = OLD.id;
+        END;",
+        "DROP TRIGGER journal_entries_search_delete;
+        DROP TRIGGER journal_entries_search_update;
+        DROP TRIGGER journal_entries_search_insert;
+        DROP TRIGGER activities_search_delete;
+        DROP TRIGGER activities_search_update;
+        DROP TRIGGER activities_search_insert;
+        DROP TRIGGER plants_search_delete;
+        DROP TRIGGER plants_search_update;
+        DROP TRIGGER plants_search_insert;
+        DROP TABLE search_index;"
+    ),
+    // Version 13: Smart lists, named plant queries. The filter is a JSON `PlantFilter`.
+    (13,
+        "CREATE TABLE smart_lists (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            filter TEXT NOT NULL DEFAULT '{}',
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            uuid TEXT
+        );
+        CREATE UNIQUE INDEX idx_smart_lists_uuid ON smart_lists(uuid);
+        CREATE TRIGGER smart_lists_assign_uuid AFTER INSERT ON smart_lists WHEN NEW.uuid IS NULL
+        BEGIN
+            UPDATE smart_lists SET uuid = lower(hex(randomblob(16))) WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER smart_lists_touch AFTER UPDATE ON smart_lists
+            WHEN NEW.updated_at IS OLD.updated_at AND NEW.uuid IS OLD.uuid
+        BEGIN
+            UPDATE smart_lists SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER smart_lists_tombstone AFTER DELETE ON smart_lists WHEN OLD.uuid IS NOT NULL
+        BEGIN
+            INSERT OR REPLACE INTO deleted_rows (table_name, uuid, deleted_at) VALUES ('smart_lists', OLD.uuid, CURRENT_TIMESTAMP);
+        END;",
+        "DROP TABLE smart_lists;
+        DELETE FROM deleted_rows WHERE table_name = 'smart_lists';"
+    ),
+    // Version 14: User-defined plant categories replace the fixed plant types. Plants keep the
+    // category key in plant_type, now a reference instead of a CHECK constraint. Rebuilding
+    // plants drops its triggers, so they are recreated.
+    (14,
+        "CREATE TABLE plant_categories (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            key TEXT NOT NULL UNIQUE,
+            name TEXT NOT NULL,
+            color TEXT NOT NULL DEFAULT '#9e9e9e',
+            icon TEXT,
+            sort_order INTEGER NOT NULL DEFAULT 0,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            uuid TEXT
+        );
+        CREATE UNIQUE INDEX idx_plant_categories_uuid ON plant_categories(uuid);
+        CREATE TRIGGER plant_categories_assign_uuid AFTER INSERT ON plant_categories WHEN NEW.uuid IS NULL
+        BEGIN
+            UPDATE plant_categories SET uuid = lower(hex(randomblob(16))) WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER plant_categories_touch AFTER UPDATE ON plant_categories
+            WHEN NEW.updated_at IS OLD.updated_at AND NEW.uuid IS OLD.uuid
+        BEGIN
+            UPDATE plant_categories SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER plant_categories_tombstone AFTER DELETE ON plant_categories WHEN OLD.uuid IS NOT NULL
+        BEGIN
+            INSERT OR REPLACE INTO deleted_rows (table_name, uuid, deleted_at) VALUES ('plant_categories', OLD.uuid, CURRENT_TIMESTAMP);
+        END;
+        INSERT INTO plant_categories (key, name, color, icon, sort_order) VALUES
+            ('vegetable_fruit', 'Vegetables', '#ff9800', '🥕', 0),
+            ('flower', 'Flowers', '#e91e63', '🌸', 1),
+            ('herb', 'Herbs', '#4caf50', '🌿', 2);
+        CREATE TABLE plants_new (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            plant_type TEXT REFERENCES plant_categories(key) ON UPDATE CASCADE ON DELETE SET NULL,
+            sun_requirements INTEGER DEFAULT 0,
+            sow_periods INTEGER DEFAULT 0,
+            plant_periods INTEGER DEFAULT 0,
+            notes TEXT,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            needs_reorder INTEGER DEFAULT 0,
+            supplier_id INTEGER REFERENCES suppliers(id) ON DELETE SET NULL,
+            packets_needed INTEGER DEFAULT 1,
+            last_price REAL,
+            uuid TEXT
+        );
+        INSERT INTO plants_new (id, name, plant_type, sun_requirements, sow_periods, plant_periods, notes, created_at, updated_at, needs_reorder, supplier_id, packets_needed, last_price, uuid)
+            SELECT id, name, plant_type, sun_requirements, sow_periods, plant_periods, notes, created_at, updated_at, needs_reorder, supplier_id, packets_needed, last_price, uuid FROM plants;
+        DROP TABLE plants;
+        ALTER TABLE plants_new RENAME TO plants;
+        CREATE INDEX idx_plants_plant_type ON plants(plant_type);
+        CREATE UNIQUE INDEX idx_plants_uuid ON plants(uuid);
+        CREATE TRIGGER plants_assign_uuid AFTER INSERT ON plants WHEN NEW.uuid IS NULL
+        BEGIN
+            UPDATE plants SET uuid = lower(hex(randomblob(16))) WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER plants_touch AFTER UPDATE ON plants
+            WHEN NEW.updated_at IS OLD.updated_at AND NEW.uuid IS OLD.uuid
+        BEGIN
+            UPDATE plants SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER plants_tombstone AFTER DELETE ON plants WHEN OLD.uuid IS NOT NULL
+        BEGIN
+            INSERT OR REPLACE INTO deleted_rows (table_name, uuid, deleted_at) VALUES ('plants', OLD.uuid, CURRENT_TIMESTAMP);
+        END;
+        CREATE TRIGGER plants_search_insert AFTER INSERT ON plants
+        BEGIN
+            INSERT INTO search_index (entity_type, entity_id, title, body) VALUES ('plant', NEW.id, NEW.name, COALESCE(NEW.notes, ''));
+        END;
+        CREATE TRIGGER plants_search_update AFTER UPDATE OF name, notes ON plants
+        BEGIN
+            DELETE FROM search_index WHERE entity_type = 'plant' AND entity_id = OLD.id;
+            INSERT INTO search_index (entity_type, entity_id, title, body) VALUES ('plant', NEW.id, NEW.name, COALESCE(NEW.notes, ''));
+        END;
+        CREATE TRIGGER plants_search_delete AFTER DELETE ON plants
+        BEGIN
+            DELETE FROM search_index WHERE entity_type = 'plant' AND entity_id = OLD.id;
+        END;",
+        // Down migration: plants in categories other than the original three become untyped
+        "CREATE TABLE plants_new (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            plant_type TEXT CHECK(plant_type IN ('vegetable_fruit', 'flower', 'herb')),
+            sun_requirements INTEGER DEFAULT 0,
+            sow_periods INTEGER DEFAULT 0,
+            plant_periods INTEGER DEFAULT 0,
+            notes TEXT,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            needs_reorder INTEGER DEFAULT 0,
+            supplier_id INTEGER REFERENCES suppliers(id) ON DELETE SET NULL,
+            packets_needed INTEGER DEFAULT 1,
+            last_price REAL,
+            uuid TEXT
+        );
+        INSERT INTO plants_new (id, name, plant_type, sun_requirements, sow_periods, plant_periods, notes, created_at, updated_at, needs_reorder, supplier_id, packets_needed, last_price, uuid)
+            SELECT id, name,
+                CASE WHEN plant_type IN ('vegetable_fruit', 'flower', 'herb') THEN plant_type END,
+                sun_requirements, sow_periods, plant_periods, notes, created_at, updated_at, needs_reorder,
+                supplier_id, packets_needed, last_price, uuid
+            FROM plants;
+        DROP TABLE plants;
+        ALTER TABLE plants_new RENAME TO plants;
+        CREATE UNIQUE INDEX idx_plants_uuid ON plants(uuid);
+        CREATE TRIGGER plants_assign_uuid AFTER INSERT ON plants WHEN NEW.uuid IS NULL
+        BEGIN
+            UPDATE plants SET uuid = lower(hex(randomblob(16))) WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER plants_touch AFTER UPDATE ON plants
+            WHEN NEW.updated_at IS OLD.updated_at AND NEW.uuid IS OLD.uuid
+        BEGIN
+            UPDATE plants SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER plants_tombstone AFTER DELETE ON plants WHEN OLD.uuid IS NOT NULL
+        BEGIN
+            INSERT OR REPLACE INTO deleted_rows (table_name, uuid, deleted_at) VALUES ('plants', OLD.uuid, CURRENT_TIMESTAMP);
+        END;
+        CREATE TRIGGER plants_search_insert AFTER INSERT ON plants
+        BEGIN
+            INSERT INTO search_index (entity_type, entity_id, title, body) VALUES ('plant', NEW.id, NEW.name, COALESCE(NEW.notes, ''));
+        END;
+        CREATE TRIGGER plants_search_update AFTER UPDATE OF name, notes ON plants
+        BEGIN
+            DELETE FROM search_index WHERE entity_type = 'plant' AND entity_id = OLD.id;
+            INSERT INTO search_index (entity_type, entity_id, title, body) VALUES ('plant', NEW.id, NEW.name, COALESCE(NEW.notes, ''));
+        END;
+        CREATE TRIGGER plants_search_delete AFTER DELETE ON plants
+        BEGIN
+            DELETE FROM search_index WHERE entity_type = 'plant' AND entity_id = OLD.id;
+        END;
+        DROP TABLE plant_categories;
+        DELETE FROM deleted_rows WHERE table_name = 'plant_categories';"
+    ),
+    // Version 15: Tags on plants and activities. The link tables have their own ids and UUIDs
+    // so merges can tell added and removed tags apart.
+    (15,
+        "CREATE TABLE tags (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            color TEXT NOT NULL DEFAULT '#607d8b',
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            uuid TEXT
+        );
+        CREATE UNIQUE INDEX idx_tags_name ON tags(name COLLATE NOCASE);
+        CREATE UNIQUE INDEX idx_tags_uuid ON tags(uuid);
+        CREATE TRIGGER tags_assign_uuid AFTER INSERT ON tags WHEN NEW.uuid IS NULL
+        BEGIN
+            UPDATE tags SET uuid = lower(hex(randomblob(16))) WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER tags_touch AFTER UPDATE ON tags
+            WHEN NEW.updated_at IS OLD.updated_at AND NEW.uuid IS OLD.uuid
+        BEGIN
+            UPDATE tags SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER tags_tombstone AFTER DELETE ON tags WHEN OLD.uuid IS NOT NULL
+        BEGIN
+            INSERT OR REPLACE INTO deleted_rows (table_name, uuid, deleted_at) VALUES ('tags', OLD.uuid, CURRENT_TIMESTAMP);
+        END;
+        CREATE TABLE plant_tags (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            plant_id INTEGER NOT NULL,
+            tag_id INTEGER NOT NULL,
+            uuid TEXT,
+            UNIQUE (plant_id, tag_id),
+            FOREIGN KEY (plant_id) REFERENCES plants(id) ON DELETE CASCADE,
+            FOREIGN KEY (tag_id) REFERENCES tags(id) ON DELETE CASCADE
+        );
+        CREATE INDEX idx_plant_tags_tag_id ON plant_tags(tag_id);
+        CREATE UNIQUE INDEX idx_plant_tags_uuid ON plant_tags(uuid);
+        CREATE TRIGGER plant_tags_assign_uuid AFTER INSERT ON plant_tags WHEN NEW.uuid IS NULL
+        BEGIN
+            UPDATE plant_tags SET uuid = lower(hex(randomblob(16))) WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER plant_tags_tombstone AFTER DELETE ON plant_tags WHEN OLD.uuid IS NOT NULL
+        BEGIN
+            INSERT OR REPLACE INTO deleted_rows (table_name, uuid, deleted_at) VALUES ('plant_tags', OLD.uuid, CURRENT_TIMESTAMP);
+        END;
+        CREATE TABLE activity_tags (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            activity_id INTEGER NOT NULL,
+            tag_id INTEGER NOT NULL,
+            uuid TEXT,
+            UNIQUE (activity_id, tag_id),
+            FOREIGN KEY (activity_id) REFERENCES activities(id) ON DELETE CASCADE,
+            FOREIGN KEY (tag_id) REFERENCES tags(id) ON DELETE CASCADE
+        );
+        CREATE INDEX idx_activity_tags_tag_id ON activity_tags(tag_id);
+        CREATE UNIQUE INDEX idx_activity_tags_uuid ON activity_tags(uuid);
+        CREATE TRIGGER activity_tags_assign_uuid AFTER INSERT ON activity_tags WHEN NEW.uuid IS NULL
+        BEGIN
+            UPDATE activity_tags SET uuid = lower(hex(randomblob(16))) WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER activity_tags_tombstone AFTER DELETE ON activity_tags WHEN OLD.uuid IS NOT NULL
+        BEGIN
+            INSERT OR REPLACE INTO deleted_rows (table_name, uuid, deleted_at) VALUES ('activity_tags', OLD.uuid, CURRENT_TIMESTAMP);
+        END;",
+        "DROP TABLE activity_tags;
+        DROP TABLE plant_tags;
+        DROP TABLE tags;
+        DELETE FROM deleted_rows WHERE table_name IN ('tags', 'plant_tags', 'activity_tags');"
+    ),
+    // Version 16: Botanical details of plants. Sizes and depths are in centimetres. The search
+    // index also covers the scientific name, cultivar and family.
+    (16,
+        "ALTER TABLE plants ADD COLUMN scientific_name TEXT;
+        ALTER TABLE plants ADD COLUMN cultivar TEXT;
+        ALTER TABLE plants ADD COLUMN family TEXT;
+        ALTER TABLE plants ADD COLUMN lifecycle TEXT CHECK(lifecycle IN ('annual', 'biennial', 'perennial'));
+        ALTER TABLE plants ADD COLUMN hardiness_zone TEXT;
+        ALTER TABLE plants ADD COLUMN sowing_depth_cm REAL;
+        ALTER TABLE plants ADD COLUMN in_row_spacing_cm REAL;
+        ALTER TABLE plants ADD COLUMN row_spacing_cm REAL;
+        ALTER TABLE plants ADD COLUMN mature_height_cm REAL;
+        ALTER TABLE plants ADD COLUMN mature_spread_cm REAL;
+        ALTER TABLE plants ADD COLUMN water_needs TEXT CHECK(water_needs IN ('low', 'medium', 'high'));
+        DROP TRIGGER plants_search_insert;
+        DROP TRIGGER plants_search_update;
+        CREATE TRIGGER plants_search_insert AFTER INSERT ON plants
+        BEGIN
+            INSERT INTO search_index (entity_type, entity_id, title, body) VALUES ('plant', NEW.id, NEW.name,
+                COALESCE(NEW.scientific_name || char(10), '') || COALESCE(NEW.cultivar || char(10), '')
+                    || COALESCE(NEW.family || char(10), '') || COALESCE(NEW.notes, ''));
+        END;
+        CREATE TRIGGER plants_search_update AFTER UPDATE OF name, notes, scientific_name, cultivar, family ON plants
+        BEGIN
+            DELETE FROM search_index WHERE entity_type = 'plant' AND entity_id = OLD.id;
+            INSERT INTO search_index (entity_type, entity_id, title, body) VALUES ('plant', NEW.id, NEW.name,
+                COALESCE(NEW.scientific_name || char(10), '') || COALESCE(NEW.cultivar || char(10), '')
+                    || COALESCE(NEW.family || char(10), '') || COALESCE(NEW.notes, ''));
+        END;",
+        "DROP TRIGGER plants_search_insert;
+        DROP TRIGGER plants_search_update;
+        ALTER TABLE plants DROP COLUMN water_needs;
+        ALTER TABLE plants DROP COLUMN mature_spread_cm;
+        ALTER TABLE plants DROP COLUMN mature_height_cm;
+        ALTER TABLE plants DROP COLUMN row_spacing_cm;
+        ALTER TABLE plants DROP COLUMN in_row_spacing_cm;
+        ALTER TABLE plants DROP COLUMN sowing_depth_cm;
+        ALTER TABLE plants DROP COLUMN hardiness_zone;
+        ALTER TABLE plants DROP COLUMN lifecycle;
+        ALTER TABLE plants DROP COLUMN family;
+        ALTER TABLE plants DROP COLUMN cultivar;
+        ALTER TABLE plants DROP COLUMN scientific_name;
+        CREATE TRIGGER plants_search_insert AFTER INSERT ON plants
+        BEGIN
+            INSERT INTO search_index (entity_type, entity_id, title, body) VALUES ('plant', NEW.id, NEW.name, COALESCE(NEW.notes, ''));
+        END;
+        CREATE TRIGGER plants_search_update AFTER UPDATE OF name, notes ON plants
+        BEGIN
+            DELETE FROM search_index WHERE entity_type = 'plant' AND entity_id = OLD.id;
+            INSERT INTO search_index (entity_type, entity_id, title, body) VALUES ('plant', NEW.id, NEW.name, COALESCE(NEW.notes, ''));
+        END;
+        DELETE FROM search_index WHERE entity_type = 'plant';
+        INSERT INTO search_index (entity_type, entity_id, title, body)
+            SELECT 'plant', id, name, COALESCE(notes, '') FROM plants;"
+    ),
+    // Version 17: Varieties under a parent species. A variety keeps its own copy of the species'
+    // values; when the species changes, varieties that still had the old value follow it.
+    (17,
+        "ALTER TABLE plants ADD COLUMN parent_id INTEGER REFERENCES plants(id) ON DELETE SET NULL;
+        CREATE INDEX idx_plants_parent_id ON plants(parent_id);
+        CREATE TRIGGER plants_varieties_inherit AFTER UPDATE OF
+            plant_type, sun_requirements, sow_periods, plant_periods, scientific_name, family, lifecycle, hardiness_zone,
+            sowing_depth_cm, in_row_spacing_cm, row_spacing_cm, mature_height_cm, mature_spread_cm, water_needs
+        ON plants
+        BEGIN
+            UPDATE plants SET plant_type = NEW.plant_type WHERE parent_id = NEW.id AND plant_type IS OLD.plant_type;
+            UPDATE plants SET sun_requirements = NEW.sun_requirements WHERE parent_id = NEW.id AND sun_requirements IS OLD.sun_requirements;
+            UPDATE plants SET sow_periods = NEW.sow_periods WHERE parent_id = NEW.id AND sow_periods IS OLD.sow_periods;
+            UPDATE plants SET plant_periods = NEW.plant_periods WHERE parent_id = NEW.id AND plant_periods IS OLD.plant_periods;
+            UPDATE plants SET scientific_name = NEW.scientific_name WHERE parent_id = NEW.id AND scientific_name IS OLD.scientific_name;
+            UPDATE plants SET family = NEW.family WHERE parent_id = NEW.id AND family IS OLD.family;
+            UPDATE plants SET lifecycle = NEW.lifecycle WHERE parent_id = NEW.id AND lifecycle IS OLD.lifecycle;
+            UPDATE plants SET hardiness_zone = NEW.hardiness_zone WHERE parent_id = NEW.id AND hardiness_zone IS OLD.hardiness_zone;
+            UPDATE plants SET sowing_depth_cm = NEW.sowing_depth_cm WHERE parent_id = NEW.id AND sowing_depth_cm IS OLD.sowing_depth_cm;
+            UPDATE plants SET in_row_spacing_cm = NEW.in_row_spacing_cm WHERE parent_id = NEW.id AND in_row_spacing_cm IS OLD.in_row_spacing_cm;
+            UPDATE plants SET row_spacing_cm = NEW.row_spacing_cm WHERE parent_id = NEW.id AND row_spacing_cm IS OLD.row_spacing_cm;
+            UPDATE plants SET mature_height_cm = NEW.mature_height_cm WHERE parent_id = NEW.id AND mature_height_cm IS OLD.mature_height_cm;
+            UPDATE plants SET mature_spread_cm = NEW.mature_spread_cm WHERE parent_id = NEW.id AND mature_spread_cm IS OLD.mature_spread_cm;
+            UPDATE plants SET water_needs = NEW.water_needs WHERE parent_id = NEW.id AND water_needs IS OLD.water_needs;
+        END;",
+        "DROP TRIGGER plants_varieties_inherit;
+        DROP INDEX idx_plants_parent_id;
+        ALTER TABLE plants DROP COLUMN parent_id;"
+    ),
+    // Version 18: Perennials. Bloom periods use the same half-month bits as sowing and planting
+    // and are shared with varieties. Plant tasks come back every few years, counted from the last
+    // time they were done or else from the year the plant was established.
+    (18,
+        "ALTER TABLE plants ADD COLUMN bloom_periods INTEGER NOT NULL DEFAULT 0;
+        ALTER TABLE plants ADD COLUMN flower_color TEXT;
+        ALTER TABLE plants ADD COLUMN established_year INTEGER;
+        DROP TRIGGER plants_varieties_inherit;
+        CREATE TRIGGER plants_varieties_inherit AFTER UPDATE OF
+            plant_type, sun_requirements, sow_periods, plant_periods, scientific_name, family, lifecycle, hardiness_zone,
+            sowing_depth_cm, in_row_spacing_cm, row_spacing_cm, mature_height_cm, mature_spread_cm, water_needs,
+            bloom_periods
+        ON plants
+        BEGIN
+            UPDATE plants SET plant_type = NEW.plant_type WHERE parent_id = NEW.id AND plant_type IS OLD.plant_type;
+            UPDATE plants SET sun_requirements = NEW.sun_requirements WHERE parent_id = NEW.id AND sun_requirements IS OLD.sun_requirements;
+            UPDATE plants SET sow_periods = NEW.sow_periods WHERE parent_id = NEW.id AND sow_periods IS OLD.sow_periods;
+            UPDATE plants SET plant_periods = NEW.plant_periods WHERE parent_id = NEW.id AND plant_periods IS OLD.plant_periods;
+            UPDATE plants SET scientific_name = NEW.scientific_name WHERE parent_id = NEW.id AND scientific_name IS OLD.scientific_name;
+            UPDATE plants SET family = NEW.family WHERE parent_id = NEW.id AND family IS OLD.family;
+            UPDATE plants SET lifecycle = NEW.lifecycle WHERE parent_id = NEW.id AND lifecycle IS OLD.lifecycle;
+            UPDATE plants SET hardiness_zone = NEW.hardiness_zone WHERE parent_id = NEW.id AND hardiness_zone IS OLD.hardiness_zone;
+            UPDATE plants SET sowing_depth_cm = NEW.sowing_depth_cm WHERE parent_id = NEW.id AND sowing_depth_cm IS OLD.sowing_depth_cm;
+            UPDATE plants SET in_row_spacing_cm = NEW.in_row_spacing_cm WHERE parent_id = NEW.id AND in_row_spacing_cm IS OLD.in_row_spacing_cm;
+            UPDATE plants SET row_spacing_cm = NEW.row_spacing_cm WHERE parent_id = NEW.id AND row_spacing_cm IS OLD.row_spacing_cm;
+            UPDATE plants SET mature_height_cm = NEW.mature_height_cm WHERE parent_id = NEW.id AND mature_height_cm IS OLD.mature_height_cm;
+            UPDATE plants SET mature_spread_cm = NEW.mature_spread_cm WHERE parent_id = NEW.id AND mature_spread_cm IS OLD.mature_spread_cm;
+            UPDATE plants SET water_needs = NEW.water_needs WHERE parent_id = NEW.id AND water_needs IS OLD.water_needs;
+            UPDATE plants SET bloom_periods = NEW.bloom_periods WHERE parent_id = NEW.id AND bloom_periods IS OLD.bloom_periods;
+        END;
+        CREATE TABLE plant_tasks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            plant_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            periods INTEGER NOT NULL DEFAULT 0,
+            interval_years INTEGER NOT NULL DEFAULT 1 CHECK(interval_years >= 1),
+            last_done_year INTEGER,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            uuid TEXT,
+            FOREIGN KEY (plant_id) REFERENCES plants(id) ON DELETE CASCADE
+        );
+        CREATE INDEX idx_plant_tasks_plant_id ON plant_tasks(plant_id);
+        CREATE UNIQUE INDEX idx_plant_tasks_uuid ON plant_tasks(uuid);
+        CREATE TRIGGER plant_tasks_assign_uuid AFTER INSERT ON plant_tasks WHEN NEW.uuid IS NULL
+        BEGIN
+            UPDATE plant_tasks SET uuid = lower(hex(randomblob(16))) WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER plant_tasks_touch AFTER UPDATE ON plant_tasks
+            WHEN NEW.updated_at IS OLD.updated_at AND NEW.uuid IS OLD.uuid
+        BEGIN
+            UPDATE plant_tasks SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER plant_tasks_tombstone AFTER DELETE ON plant_tasks WHEN OLD.uuid IS NOT NULL
+        BEGIN
+            INSERT OR REPLACE INTO deleted_rows (table_name, uuid, deleted_at) VALUES ('plant_tasks', OLD.uuid, CURRENT_TIMESTAMP);
+        END;",
+        "DROP TABLE plant_tasks;
+        DELETE FROM deleted_rows WHERE table_name = 'plant_tasks';
+        DROP TRIGGER plants_varieties_inherit;
+        CREATE TRIGGER plants_varieties_inherit AFTER UPDATE OF
+            plant_type, sun_requirements, sow_periods, plant_periods, scientific_name, family, lifecycle, hardiness_zone,
+            sowing_depth_cm, in_row_spacing_cm, row_spacing_cm, mature_height_cm, mature_spread_cm, water_needs
+        ON plants
+        BEGIN
+            UPDATE plants SET plant_type = NEW.plant_type WHERE parent_id = NEW.id AND plant_type IS OLD.plant_type;
+            UPDATE plants SET sun_requirements = NEW.sun_requirements WHERE parent_id = NEW.id AND sun_requirements IS OLD.sun_requirements;
+            UPDATE plants SET sow_periods = NEW.sow_periods WHERE parent_id = NEW.id AND sow_periods IS OLD.sow_periods;
+            UPDATE plants SET plant_periods = NEW.plant_periods WHERE parent_id = NEW.id AND plant_periods IS OLD.plant_periods;
+            UPDATE plants SET scientific_name = NEW.scientific_name WHERE parent_id = NEW.id AND scientific_name IS OLD.scientific_name;
+            UPDATE plants SET family = NEW.family WHERE parent_id = NEW.id AND family IS OLD.family;
+            UPDATE plants SET lifecycle = NEW.lifecycle WHERE parent_id = NEW.id AND lifecycle IS OLD.lifecycle;
+            UPDATE plants SET hardiness_zone = NEW.hardiness_zone WHERE parent_id = NEW.id AND hardiness_zone IS OLD.hardiness_zone;
+            UPDATE plants SET sowing_depth_cm = NEW.sowing_depth_cm WHERE parent_id = NEW.id AND sowing_depth_cm IS OLD.sowing_depth_cm;
+            UPDATE plants SET in_row_spacing_cm = NEW.in_row_spacing_cm WHERE parent_id = NEW.id AND in_row_spacing_cm IS OLD.in_row_spacing_cm;
+            UPDATE plants SET row_spacing_cm = NEW.row_spacing_cm WHERE parent_id = NEW.id AND row_spacing_cm IS OLD.row_spacing_cm;
+            UPDATE plants SET mature_height_cm = NEW.mature_height_cm WHERE parent_id = NEW.id AND mature_height_cm IS OLD.mature_height_cm;
+            UPDATE plants SET mature_spread_cm = NEW.mature_spread_cm WHERE parent_id = NEW.id AND mature_spread_cm IS OLD.mature_spread_cm;
+            UPDATE plants SET water_needs = NEW.water_needs WHERE parent_id = NEW.id AND water_needs IS OLD.water_needs;
+        END;
+        ALTER TABLE plants DROP COLUMN established_year;
+        ALTER TABLE plants DROP COLUMN flower_color;
+        ALTER TABLE plants DROP COLUMN bloom_periods;"
+    ),
+    // Version 19: Photos of journal entries and plant tasks. Exactly one owner column is set.
+    (19,
+        "CREATE TABLE plant_photos_new (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            plant_id INTEGER,
+            activity_id INTEGER,
+            journal_entry_id INTEGER,
+            plant_task_id INTEGER,
+            sort_order INTEGER DEFAULT 0,
+            image_data BLOB,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            uuid TEXT,
+            thumbnail BLOB,
+            original_file TEXT,
+            caption TEXT,
+            taken_at DATETIME,
+            is_primary INTEGER NOT NULL DEFAULT 0,
+            content_hash TEXT,
+            perceptual_hash INTEGER,
+            CHECK ((plant_id IS NOT NULL) + (activity_id IS NOT NULL) + (journal_entry_id IS NOT NULL) + (plant_task_id IS NOT NULL) = 1),
+            FOREIGN KEY (plant_id) REFERENCES plants(id) ON DELETE CASCADE,
+            FOREIGN KEY (activity_id) REFERENCES activities(id) ON DELETE CASCADE,
+            FOREIGN KEY (journal_entry_id) REFERENCES journal_entries(id) ON DELETE CASCADE,
+            FOREIGN KEY (plant_task_id) REFERENCES plant_tasks(id) ON DELETE CASCADE
+        );
+        INSERT INTO plant_photos_new (
+            id, plant_id, activity_id, sort_order, image_data, created_at, uuid, thumbnail, original_file, caption, taken_at, is_primary, content_hash, perceptual_hash
+        )
+        SELECT id, plant_id, activity_id, sort_order, image_data, created_at, uuid, thumbnail, original_file, caption, taken_at, is_primary, content_hash, perceptual_hash
+        FROM plant_photos;
+        DROP TABLE plant_photos;
+        ALTER TABLE plant_photos_new RENAME TO plant_photos;
+        CREATE INDEX idx_plant_photos_plant_id ON plant_photos(plant_id);
+        CREATE INDEX idx_plant_photos_activity_id ON plant_photos(activity_id);
+        CREATE INDEX idx_plant_photos_journal_entry_id ON plant_photos(journal_entry_id);
+        CREATE INDEX idx_plant_photos_plant_task_id ON plant_photos(plant_task_id);
+        CREATE UNIQUE INDEX idx_plant_photos_uuid ON plant_photos(uuid);
+        CREATE TRIGGER plant_photos_assign_uuid AFTER INSERT ON plant_photos WHEN NEW.uuid IS NULL
+        BEGIN
+            UPDATE plant_photos SET uuid = lower(hex(randomblob(16))) WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER plant_photos_tombstone AFTER DELETE ON plant_photos WHEN OLD.uuid IS NOT NULL
+        BEGIN
+            INSERT OR REPLACE INTO deleted_rows (table_name, uuid, deleted_at) VALUES ('plant_photos', OLD.uuid, CURRENT_TIMESTAMP);
+        END;",
+        // Down migration: photos of journal entries and tasks have nowhere to go and are dropped
+        "CREATE TABLE plant_photos_new (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            plant_id INTEGER,
+            activity_id INTEGER,
+            sort_order INTEGER DEFAULT 0,
+            image_data BLOB,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            uuid TEXT,
+            thumbnail BLOB,
+            original_file TEXT,
+            caption TEXT,
+            taken_at DATETIME,
+            is_primary INTEGER NOT NULL DEFAULT 0,
+            content_hash TEXT,
+            perceptual_hash INTEGER,
+            CHECK ((plant_id IS NOT NULL) + (activity_id IS NOT NULL) = 1),
+            FOREIGN KEY (plant_id) REFERENCES plants(id) ON DELETE CASCADE,
+            FOREIGN KEY (activity_id) REFERENCES activities(id) ON DELETE CASCADE
+        );
+        INSERT INTO plant_photos_new (
+            id, plant_id, activity_id, sort_order, image_data, created_at, uuid, thumbnail, original_file, caption, taken_at, is_primary, content_hash, perceptual_hash
+        )
+        SELECT id, plant_id, activity_id, sort_order, image_data, created_at, uuid, thumbnail, original_file, caption, taken_at, is_primary, content_hash, perceptual_hash
+        FROM plant_photos WHERE plant_id IS NOT NULL OR activity_id IS NOT NULL;
+        DROP TABLE plant_photos;
+        ALTER TABLE plant_photos_new RENAME TO plant_photos;
+        CREATE INDEX idx_plant_photos_plant_id ON plant_photos(plant_id);
+        CREATE INDEX idx_plant_photos_activity_id ON plant_photos(activity_id);
+        CREATE UNIQUE INDEX idx_plant_photos_uuid ON plant_photos(uuid);
+        CREATE TRIGGER plant_photos_assign_uuid AFTER INSERT ON plant_photos WHEN NEW.uuid IS NULL
+        BEGIN
+            UPDATE plant_photos SET uuid = lower(hex(randomblob(16))) WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER plant_photos_tombstone AFTER DELETE ON plant_photos WHEN OLD.uuid IS NOT NULL
+        BEGIN
+            INSERT OR REPLACE INTO deleted_rows (table_name, uuid, deleted_at) VALUES ('plant_photos', OLD.uuid, CURRENT_TIMESTAMP);
+        END;"
+    ),
+    // Version 20: Varieties list the fields they inherit from their species instead of following
+    // it wherever their value happens to be equal. Existing varieties inherit what they share now.
+    (20,
+        "ALTER TABLE plants ADD COLUMN inherited_fields TEXT NOT NULL DEFAULT '[]';
+        DROP TRIGGER plants_varieties_inherit;
+        UPDATE plants SET inherited_fields = (
+            SELECT json_group_array(field) FROM (
+                SELECT 'plant_type' AS field FROM plants AS species WHERE species.id = plants.parent_id AND plants.plant_type IS species.plant_type
+                UNION ALL SELECT 'sun_requirements' FROM plants AS species WHERE species.id = plants.parent_id AND plants.sun_requirements IS species.sun_requirements
+                UNION ALL SELECT 'sow_periods' FROM plants AS species WHERE species.id = plants.parent_id AND plants.sow_periods IS species.sow_periods
+                UNION ALL SELECT 'plant_periods' FROM plants AS species WHERE species.id = plants.parent_id AND plants.plant_periods IS species.plant_periods
+                UNION ALL SELECT 'scientific_name' FROM plants AS species WHERE species.id = plants.parent_id AND plants.scientific_name IS species.scientific_name
+                UNION ALL SELECT 'family' FROM plants AS species WHERE species.id = plants.parent_id AND plants.family IS species.family
+                UNION ALL SELECT 'lifecycle' FROM plants AS species WHERE species.id = plants.parent_id AND plants.lifecycle IS species.lifecycle
+                UNION ALL SELECT 'hardiness_zone' FROM plants AS species WHERE species.id = plants.parent_id AND plants.hardiness_zone IS species.hardiness_zone
+                UNION ALL SELECT 'sowing_depth_cm' FROM plants AS species WHERE species.id = plants.parent_id AND plants.sowing_depth_cm IS species.sowing_depth_cm
+                UNION ALL SELECT 'in_row_spacing_cm' FROM plants AS species WHERE species.id = plants.parent_id AND plants.in_row_spacing_cm IS species.in_row_spacing_cm
+                UNION ALL SELECT 'row_spacing_cm' FROM plants AS species WHERE species.id = plants.parent_id AND plants.row_spacing_cm IS species.row_spacing_cm
+                UNION ALL SELECT 'mature_height_cm' FROM plants AS species WHERE species.id = plants.parent_id AND plants.mature_height_cm IS species.mature_height_cm
+                UNION ALL SELECT 'mature_spread_cm' FROM plants AS species WHERE species.id = plants.parent_id AND plants.mature_spread_cm IS species.mature_spread_cm
+                UNION ALL SELECT 'water_needs' FROM plants AS species WHERE species.id = plants.parent_id AND plants.water_needs IS species.water_needs
+                UNION ALL SELECT 'bloom_periods' FROM plants AS species WHERE species.id = plants.parent_id AND plants.bloom_periods IS species.bloom_periods
+            )
+        ) WHERE parent_id IS NOT NULL;
+        CREATE TRIGGER plants_varieties_inherit AFTER UPDATE OF
+            plant_type, sun_requirements, sow_periods, plant_periods, scientific_name, family, lifecycle, hardiness_zone,
+            sowing_depth_cm, in_row_spacing_cm, row_spacing_cm, mature_height_cm, mature_spread_cm, water_needs,
+            bloom_periods
+        ON plants
+        BEGIN
+            UPDATE plants SET plant_type = NEW.plant_type WHERE parent_id = NEW.id AND plant_type IS NOT NEW.plant_type
+                AND EXISTS(SELECT 1 FROM json_each(plants.inherited_fields) WHERE value = 'plant_type');
+            UPDATE plants SET sun_requirements = NEW.sun_requirements WHERE parent_id = NEW.id AND sun_requirements IS NOT NEW.sun_requirements
+                AND EXISTS(SELECT 1 FROM json_each(plants.inherited_fields) WHERE value = 'sun_requirements');
+            UPDATE plants SET sow_periods = NEW.sow_periods WHERE parent_id = NEW.id AND sow_periods IS NOT NEW.sow_periods
+                AND EXISTS(SELECT 1 FROM json_each(plants.inherited_fields) WHERE value = 'sow_periods');
+            UPDATE plants SET plant_periods = NEW.plant_periods WHERE parent_id = NEW.id AND plant_periods IS NOT NEW.plant_periods
+                AND EXISTS(SELECT 1 FROM json_each(plants.inherited_fields) WHERE value = 'plant_periods');
+            UPDATE plants SET scientific_name = NEW.scientific_name WHERE parent_id = NEW.id AND scientific_name IS NOT NEW.scientific_name
+                AND EXISTS(SELECT 1 FROM json_each(plants.inherited_fields) WHERE value = 'scientific_name');
+            UPDATE plants SET family = NEW.family WHERE parent_id = NEW.id AND family IS NOT NEW.family
+                AND EXISTS(SELECT 1 FROM json_each(plants.inherited_fields) WHERE value = 'family');
+            UPDATE plants SET lifecycle = NEW.lifecycle WHERE parent_id = NEW.id AND lifecycle IS NOT NEW.lifecycle
+                AND EXISTS(SELECT 1 FROM json_each(plants.inherited_fields) WHERE value = 'lifecycle');
+            UPDATE plants SET hardiness_zone = NEW.hardiness_zone WHERE parent_id = NEW.id AND hardiness_zone IS NOT NEW.hardiness_zone
+                AND EXISTS(SELECT 1 FROM json_each(plants.inherited_fields) WHERE value = 'hardiness_zone');
+            UPDATE plants SET sowing_depth_cm = NEW.sowing_depth_cm WHERE parent_id = NEW.id AND sowing_depth_cm IS NOT NEW.sowing_depth_cm
+                AND EXISTS(SELECT 1 FROM json_each(plants.inherited_fields) WHERE value = 'sowing_depth_cm');
+            UPDATE plants SET in_row_spacing_cm = NEW.in_row_spacing_cm WHERE parent_id = NEW.id AND in_row_spacing_cm IS NOT NEW.in_row_spacing_cm
+                AND EXISTS(SELECT 1 FROM json_each(plants.inherited_fields) WHERE value = 'in_row_spacing_cm');
+            UPDATE plants SET row_spacing_cm = NEW.row_spacing_cm WHERE parent_id = NEW.id AND row_spacing_cm IS NOT NEW.row_spacing_cm
+                AND EXISTS(SELECT 1 FROM json_each(plants.inherited_fields) WHERE value = 'row_spacing_cm');
+            UPDATE plants SET mature_height_cm = NEW.mature_height_cm WHERE parent_id = NEW.id AND mature_height_cm IS NOT NEW.mature_height_cm
+                AND EXISTS(SELECT 1 FROM json_each(plants.inherited_fields) WHERE value = 'mature_height_cm');
+            UPDATE plants SET mature_spread_cm = NEW.mature_spread_cm WHERE parent_id = NEW.id AND mature_spread_cm IS NOT NEW.mature_spread_cm
+                AND EXISTS(SELECT 1 FROM json_each(plants.inherited_fields) WHERE value = 'mature_spread_cm');
+            UPDATE plants SET water_needs = NEW.water_needs WHERE parent_id = NEW.id AND water_needs IS NOT NEW.water_needs
+                AND EXISTS(SELECT 1 FROM json_each(plants.inherited_fields) WHERE value = 'water_needs');
+            UPDATE plants SET bloom_periods = NEW.bloom_periods WHERE parent_id = NEW.id AND bloom_periods IS NOT NEW.bloom_periods
+                AND EXISTS(SELECT 1 FROM json_each(plants.inherited_fields) WHERE value = 'bloom_periods');
+        END;",
+        "DROP TRIGGER plants_varieties_inherit;
+        CREATE TRIGGER plants_varieties_inherit AFTER UPDATE OF
+            plant_type, sun_requirements, sow_periods, plant_periods, scientific_name, family, lifecycle, hardiness_zone,
+            sowing_depth_cm, in_row_spacing_cm, row_spacing_cm, mature_height_cm, mature_spread_cm, water_needs,
+            bloom_periods
+        ON plants
+        BEGIN
+            UPDATE plants SET plant_type = NEW.plant_type WHERE parent_id = NEW.id AND plant_type IS OLD.plant_type;
+            UPDATE plants SET sun_requirements = NEW.sun_requirements WHERE parent_id = NEW.id AND sun_requirements IS OLD.sun_requirements;
+            UPDATE plants SET sow_periods = NEW.sow_periods WHERE parent_id = NEW.id AND sow_periods IS OLD.sow_periods;
+            UPDATE plants SET plant_periods = NEW.plant_periods WHERE parent_id = NEW.id AND plant_periods IS OLD.plant_periods;
+            UPDATE plants SET scientific_name = NEW.scientific_name WHERE parent_id = NEW.id AND scientific_name IS OLD.scientific_name;
+            UPDATE plants SET family = NEW.family WHERE parent_id = NEW.id AND family IS OLD.family;
+            UPDATE plants SET lifecycle = NEW.lifecycle WHERE parent_id = NEW.id AND lifecycle IS OLD.lifecycle;
+            UPDATE plants SET hardiness_zone = NEW.hardiness_zone WHERE parent_id = NEW.id AND hardiness_zone IS OLD.hardiness_zone;
+            UPDATE plants SET sowing_depth_cm = NEW.sowing_depth_cm WHERE parent_id = NEW.id AND sowing_depth_cm IS OLD.sowing_depth_cm;
+            UPDATE plants SET in_row_spacing_cm = NEW.in_row_spacing_cm WHERE parent_id = NEW.id AND in_row_spacing_cm IS OLD.in_row_spacing_cm;
+            UPDATE plants SET row_spacing_cm = NEW.row_spacing_cm WHERE parent_id = NEW.id AND row_spacing_cm IS OLD.row_spacing_cm;
+            UPDATE plants SET mature_height_cm = NEW.mature_height_cm WHERE parent_id = NEW.id AND mature_height_cm IS OLD.mature_height_cm;
+            UPDATE plants SET mature_spread_cm = NEW.mature_spread_cm WHERE parent_id = NEW.id AND mature_spread_cm IS OLD.mature_spread_cm;
+            UPDATE plants SET water_needs = NEW.water_needs WHERE parent_id = NEW.id AND water_needs IS OLD.water_needs;
+            UPDATE plants SET bloom_periods = NEW.bloom_periods WHERE parent_id = NEW.id AND bloom_periods IS OLD.bloom_periods;
+        END;
+        ALTER TABLE plants DROP COLUMN inherited_fields;"
+    ),
 ];
 
 fn get_schema_version(conn: &Connection) -> Result<i32> {
@@ -198,59 +1437,172 @@ fn table_exists(conn: &Connection, table_name: &str) -> Result<bool> {
     Ok(count > 0)
 }
 
-pub fn run_migrations(conn: &Connection) -> Result<()> {
-    let mut current_version = get_schema_version(conn)?;
+pub fn latest_schema_version() -> i32 {
+    MIGRATIONS.last().map(|(version, _, _)| *version).unwrap_or(0)
+}
+
+pub fn current_schema_version(conn: &Connection) -> Result<i32> {
+    get_schema_version(conn)
+}
+
+pub fn run_migrations(conn: &mut Connection) -> std::result::Result<(), String> {
+    migrate_to(conn, latest_schema_version())
+}
+
+/// Migrate up or down to `target`. Each step runs in its own transaction together with its
+/// version bump, so a failing step rolls back and leaves the database at the previous version.
+pub fn migrate_to(conn: &mut Connection, target: i32) -> std::result::Result<(), String> {
+    // Table rebuilds drop and recreate tables, which would cascade deletes with foreign keys on.
+    // The pragma is a no-op inside a transaction, so it has to be toggled around the steps.
+    conn.execute_batch("PRAGMA foreign_keys = OFF;").map_err(|e| e.to_string())?;
+    let result = apply_migrations(conn, target);
+    conn.execute_batch("PRAGMA foreign_keys = ON;").map_err(|e| e.to_string())?;
+    result
+}
+
+fn apply_migrations(conn: &mut Connection, target: i32) -> std::result::Result<(), String> {
+    let latest = latest_schema_version();
+    if target < 1 || target > latest {
+        return Err(format!("Unknown schema version {} (this version of the app supports 1 to {})", target, latest));
+    }
+
+    let mut current_version = get_schema_version(conn).map_err(|e| e.to_string())?;
 
     // Detect existing database (plants table exists but version is 0)
-    if current_version == 0 && table_exists(conn, "plants")? {
+    if current_version == 0 && table_exists(conn, "plants").map_err(|e| e.to_string())? {
         // Existing database from before migration system - treat as v1
         current_version = 1;
-        set_schema_version(conn, 1)?;
+        set_schema_version(conn, 1).map_err(|e| e.to_string())?;
         println!("Detected existing database, setting version to 1");
     }
 
+    if current_version > latest {
+        return Err(format!(
+            "The database is at schema version {}, which is newer than this version of the app supports ({}). Please update the app.",
+            current_version, latest
+        ));
+    }
+
     // Run pending up migrations sequentially
     for (version, up_sql, _down_sql) in MIGRATIONS {
-        if *version > current_version {
+        if *version > current_version && *version <= target {
             println!("Running migration to version {}", version);
-            conn.execute_batch(up_sql)?;
-            set_schema_version(conn, *version)?;
+            run_step(conn, up_sql, *version)
+                .map_err(|e| format!("Migration to version {} failed: {}", version, e))?;
             println!("Migration to version {} complete", version);
         }
     }
 
+    // Run down migrations newest first
+    for (version, _up_sql, down_sql) in MIGRATIONS.iter().rev() {
+        if *version <= current_version && *version > target {
+            if down_sql.is_empty() {
+                return Err(format!("Version {} has no down migration", version));
+            }
+            println!("Rolling back migration {}", version);
+            run_step(conn, down_sql, version - 1)
+                .map_err(|e| format!("Rolling back version {} failed: {}", version, e))?;
+            println!("Rolled back to version {}", version - 1);
+        }
+    }
+
     Ok(())
 }
 
-pub fn backup_database(app: &tauri::AppHandle, db_path: &PathBuf) {
-    use tauri::Manager;
+/// Run one migration step and record the resulting version, all or nothing.
+fn run_step(conn: &mut Connection, sql: &str, resulting_version: i32) -> Result<()> {
+    let tx = conn.transaction()?;
+    tx.execute_batch(sql)?;
+    set_schema_version(&tx, resulting_version)?;
 
-    // Only backup if the database file exists
-    if !db_path.exists() {
-        return;
+    // Foreign keys are off while migrating, so verify the step didn't leave broken references
+    let violations: i64 = tx.query_row("SELECT COUNT(*) FROM pragma_foreign_key_check", [], |row| row.get(0))?;
+    if violations > 0 {
+        return Err(rusqlite::Error::SqliteFailure(
+            rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CONSTRAINT_FOREIGNKEY),
+            Some(format!("{} foreign key violation(s)", violations)),
+        ));
     }
 
-    // Create backups directory in app data folder
-    let app_dir = app
-        .path()
-        .app_data_dir()
-        .expect("Failed to get app data dir");
-    let backup_dir = app_dir.join("backups");
-    if let Err(e) = std::fs::create_dir_all(&backup_dir) {
-        eprintln!("Failed to create backup directory: {}", e);
-        return;
-    }
+    tx.commit()
+}
+
+/// Error from opening or migrating the database at startup, shown instead of the normal UI.
+#[derive(Default)]
+pub struct StartupState {
+    pub error: Mutex<Option<String>>,
+    /// The database is encrypted and waiting for its passphrase
+    pub locked: Mutex<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IntegrityReport {
+    pub ok: bool,
+    /// Output of `PRAGMA integrity_check`, a single "ok" when the file is healthy
+    pub integrity_messages: Vec<String>,
+    pub orphaned_photos_removed: usize,
+    pub dangling_suppliers_cleared: usize,
+    /// Remaining `PRAGMA foreign_key_check` violations, as "table row -> parent"
+    pub foreign_key_violations: Vec<String>,
+    pub bytes_reclaimed: i64,
+}
 
-    // Generate backup filename with timestamp
-    let timestamp = Local::now().format("%Y-%m-%d_%H-%M-%S");
-    let backup_filename = format!("tuinapp_{}.db", timestamp);
-    let backup_path = backup_dir.join(&backup_filename);
+fn database_size(conn: &Connection) -> Result<i64> {
+    conn.query_row(
+        "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+        [],
+        |row| row.get(0),
+    )
+}
+
+/// Run SQLite's integrity check, remove orphaned rows and compact the file if anything was removed.
+pub fn check_integrity(conn: &Connection) -> Result<IntegrityReport> {
+    let integrity_messages = conn
+        .prepare("PRAGMA integrity_check")?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>>>()?;
+
+    let size_before = database_size(conn)?;
 
-    // Copy database to backup location
-    match std::fs::copy(db_path, &backup_path) {
-        Ok(_) => println!("Database backed up to: {}", backup_path.display()),
-        Err(e) => eprintln!("Failed to backup database: {}", e),
+    // NOT EXISTS rather than NOT IN: `NULL NOT IN (<no rows>)` is true, which would remove the
+    // photos of every other kind of owner whenever one owner table is empty
+    let orphaned_photos_removed = conn.execute(
+        "DELETE FROM plant_photos
+         WHERE (plant_id IS NOT NULL AND NOT EXISTS (SELECT 1 FROM plants WHERE id = plant_photos.plant_id))
+            OR (activity_id IS NOT NULL AND NOT EXISTS (SELECT 1 FROM activities WHERE id = plant_photos.activity_id))
+            OR (journal_entry_id IS NOT NULL AND NOT EXISTS (SELECT 1 FROM journal_entries WHERE id = plant_photos.journal_entry_id))
+            OR (plant_task_id IS NOT NULL AND NOT EXISTS (SELECT 1 FROM plant_tasks WHERE id = plant_photos.plant_task_id))",
+        [],
+    )?;
+    let dangling_suppliers_cleared = conn.execute(
+        "UPDATE plants SET supplier_id = NULL WHERE supplier_id IS NOT NULL AND supplier_id NOT IN (SELECT id FROM suppliers)",
+        [],
+    )?;
+
+    let foreign_key_violations = conn
+        .prepare("PRAGMA foreign_key_check")?
+        .query_map([], |row| {
+            let table: String = row.get(0)?;
+            let rowid: Option<i64> = row.get(1)?;
+            let parent: String = row.get(2)?;
+            Ok(format!("{} {} -> {}", table, rowid.map(|r| r.to_string()).unwrap_or_default(), parent))
+        })?
+        .collect::<Result<Vec<_>>>()?;
+
+    // Deleted photo BLOBs leave free pages behind; give the space back
+    if orphaned_photos_removed > 0 {
+        conn.execute_batch("VACUUM;")?;
     }
+    let bytes_reclaimed = size_before - database_size(conn)?;
+
+    Ok(IntegrityReport {
+        ok: integrity_messages == ["ok"] && foreign_key_violations.is_empty(),
+        integrity_messages,
+        orphaned_photos_removed,
+        dangling_suppliers_cleared,
+        foreign_key_violations,
+        bytes_reclaimed,
+    })
 }
 
 pub fn get_default_db_path(app: &tauri::AppHandle) -> PathBuf {
@@ -263,13 +1615,167 @@ pub fn get_default_db_path(app: &tauri::AppHandle) -> PathBuf {
     app_dir.join("tuinapp.db")
 }
 
-pub fn get_db_path(app: &tauri::AppHandle) -> PathBuf {
-    let config = load_config(app);
-    if let Some(custom_path) = config.database_path {
-        let path = PathBuf::from(&custom_path);
+/// Where a profile keeps its database when it has no custom location: the default profile
+/// uses the original location, other profiles get a file in the `gardens` folder.
+fn get_profile_default_db_path(app: &tauri::AppHandle, profile: &Profile) -> PathBuf {
+    let default_path = get_default_db_path(app);
+    if profile.id == DEFAULT_PROFILE_ID {
+        return default_path;
+    }
+    let gardens_dir = default_path.with_file_name("gardens");
+    std::fs::create_dir_all(&gardens_dir).expect("Failed to create gardens dir");
+    gardens_dir.join(format!("{}.db", profile.id))
+}
+
+pub fn get_profile_db_path(app: &tauri::AppHandle, profile: &Profile) -> PathBuf {
+    if let Some(custom_path) = &profile.database_path {
+        let path = PathBuf::from(custom_path);
         if path.exists() || path.parent().map(|p| p.exists()).unwrap_or(false) {
             return path;
         }
     }
-    get_default_db_path(app)
+    get_profile_default_db_path(app, profile)
+}
+
+pub fn get_db_path(app: &tauri::AppHandle) -> PathBuf {
+    let config = load_config(app);
+    get_profile_db_path(app, config.active())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_database() -> Connection {
+        open_connection(&PathBuf::from(":memory:"), None).unwrap()
+    }
+
+    /// Every table, index and trigger with the columns of the tables, to compare schemas
+    /// regardless of whether a table was altered or rebuilt.
+    fn schema(conn: &Connection) -> Vec<String> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT m.type || ' ' || m.name || COALESCE(' ' || (
+                     SELECT group_concat(c.name || ':' || c.type || ':' || c.\"notnull\" || ':' || COALESCE(c.dflt_value, ''), ',')
+                     FROM pragma_table_info(m.name) c
+                 ), '')
+                 FROM sqlite_master m
+                 WHERE m.name NOT LIKE 'sqlite_%'
+                 ORDER BY m.type, m.name",
+            )
+            .unwrap();
+        let rows = stmt.query_map([], |row| row.get(0)).unwrap();
+        rows.collect::<Result<Vec<String>>>().unwrap()
+    }
+
+    #[test]
+    fn every_migration_rolls_back_to_the_previous_schema() {
+        let mut conn = new_database();
+        let mut schemas = Vec::new();
+        for (version, _, _) in MIGRATIONS {
+            migrate_to(&mut conn, *version).unwrap();
+            assert_eq!(current_schema_version(&conn).unwrap(), *version);
+            schemas.push(schema(&conn));
+        }
+
+        for (version, _, _) in MIGRATIONS.iter().rev().skip(1) {
+            migrate_to(&mut conn, *version).unwrap();
+            assert_eq!(current_schema_version(&conn).unwrap(), *version);
+            assert_eq!(schema(&conn), schemas[*version as usize - 1], "schema after rolling back to version {}", version);
+        }
+
+        run_migrations(&mut conn).unwrap();
+        assert_eq!(schema(&conn), schemas[schemas.len() - 1]);
+    }
+
+    #[test]
+    fn rolling_back_and_forward_keeps_plants() {
+        let mut conn = new_database();
+        run_migrations(&mut conn).unwrap();
+        conn.execute("INSERT INTO plants (name, sow_periods, plant_periods) VALUES ('Tomato', 6, 24)", []).unwrap();
+
+        migrate_to(&mut conn, 1).unwrap();
+        run_migrations(&mut conn).unwrap();
+
+        let plant: (String, i32, i32) = conn
+            .query_row("SELECT name, sow_periods, plant_periods FROM plants", [], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })
+            .unwrap();
+        assert_eq!(plant, ("Tomato".to_string(), 6, 24));
+    }
+
+    #[test]
+    fn existing_varieties_inherit_what_they_share_with_their_species() {
+        let mut conn = new_database();
+        migrate_to(&mut conn, 19).unwrap();
+        conn.execute_batch(
+            "INSERT INTO plants (id, name, plant_type, sow_periods, family) VALUES (1, 'Tomato', 'vegetable_fruit', 6, 'Solanaceae');
+             INSERT INTO plants (name, parent_id, plant_type, sow_periods, family) VALUES ('Tomato - Roma', 1, 'vegetable_fruit', 24, NULL);",
+        )
+        .unwrap();
+
+        run_migrations(&mut conn).unwrap();
+
+        let inherited: String = conn
+            .query_row("SELECT inherited_fields FROM plants WHERE parent_id = 1", [], |row| row.get(0))
+            .unwrap();
+        let inherited: Vec<String> = serde_json::from_str(&inherited).unwrap();
+        assert!(inherited.contains(&"plant_type".to_string()));
+        assert!(inherited.contains(&"sun_requirements".to_string()));
+        assert!(!inherited.contains(&"sow_periods".to_string()));
+        assert!(!inherited.contains(&"family".to_string()));
+        let species: String = conn.query_row("SELECT inherited_fields FROM plants WHERE id = 1", [], |row| row.get(0)).unwrap();
+        assert_eq!(species, "[]");
+    }
+
+    #[test]
+    fn integrity_check_keeps_photos_when_another_owner_table_is_empty() {
+        let mut conn = new_database();
+        run_migrations(&mut conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO plants (name) VALUES ('Tomato');
+             INSERT INTO plant_photos (plant_id, image_data) VALUES (1, x'01'), (1, x'02');",
+        )
+        .unwrap();
+
+        let report = check_integrity(&conn).unwrap();
+        assert_eq!(report.orphaned_photos_removed, 0);
+        assert!(report.ok);
+        let photos: i64 = conn.query_row("SELECT COUNT(*) FROM plant_photos", [], |row| row.get(0)).unwrap();
+        assert_eq!(photos, 2);
+    }
+
+    #[test]
+    fn integrity_check_removes_orphaned_photos() {
+        let mut conn = new_database();
+        run_migrations(&mut conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO activities (name) VALUES ('Mulch');
+             INSERT INTO plant_photos (activity_id, image_data) VALUES (1, x'01');
+             PRAGMA foreign_keys = OFF;
+             INSERT INTO plant_photos (plant_id, image_data) VALUES (42, x'02');
+             INSERT INTO plant_photos (journal_entry_id, image_data) VALUES (42, x'03');
+             PRAGMA foreign_keys = ON;",
+        )
+        .unwrap();
+
+        let report = check_integrity(&conn).unwrap();
+        assert_eq!(report.orphaned_photos_removed, 2);
+        assert!(report.ok);
+        let remaining: Vec<u8> = conn.query_row("SELECT image_data FROM plant_photos", [], |row| row.get(0)).unwrap();
+        assert_eq!(remaining, [1]);
+    }
+
+    #[test]
+    fn migrate_to_refuses_unknown_versions() {
+        let mut conn = new_database();
+        run_migrations(&mut conn).unwrap();
+        assert!(migrate_to(&mut conn, 0).is_err());
+        assert!(migrate_to(&mut conn, latest_schema_version() + 1).is_err());
+
+        set_schema_version(&conn, latest_schema_version() + 1).unwrap();
+        assert!(run_migrations(&mut conn).is_err());
+        assert_eq!(current_schema_version(&conn).unwrap(), latest_schema_version() + 1);
+    }
 }
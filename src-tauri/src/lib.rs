@@ -1,10 +1,38 @@
+mod backup;
 mod commands;
 mod db;
+mod location;
+mod merge;
 mod models;
+mod photos;
+mod search;
+mod sync;
 
-use db::{backup_database, get_db_path, run_migrations, Database};
+use backup::{backup_database, start_backup_scheduler};
+use db::{get_db_path, is_readable, run_migrations, Database, StartupState};
+use sync::{lease_path, release_lease, start_sync_watcher, SyncState};
 use tauri::Manager;
 
+/// Back up the database and bring its schema up to date. A failed migration is rolled back;
+/// keep the app running so the UI can show the error and offer a retry or rollback instead
+/// of panicking at startup.
+pub(crate) fn prepare_database(app: &tauri::AppHandle, take_backup: bool) {
+    let db = app.state::<Database>();
+    let mut conn = db.conn.lock().unwrap();
+
+    // Backup database before migrating it
+    if take_backup {
+        if let Err(e) = backup_database(app, &conn) {
+            eprintln!("{}", e);
+        }
+    }
+
+    if let Err(e) = run_migrations(&mut conn) {
+        eprintln!("Failed to run migrations: {}", e);
+        *app.state::<StartupState>().error.lock().unwrap() = Some(e);
+    }
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
@@ -13,18 +41,24 @@ pub fn run() {
         .plugin(tauri_plugin_fs::init())
         .setup(|app| {
             let db_path = get_db_path(&app.handle());
-
-            // Backup database before opening
-            backup_database(&app.handle(), &db_path);
+            let is_new = !db_path.exists();
 
             let database = Database::new(&db_path).expect("Failed to open database");
+            app.manage(database);
+            app.manage(StartupState::default());
+            app.manage(SyncState::new());
 
-            {
-                let conn = database.conn.lock().unwrap();
-                run_migrations(&conn).expect("Failed to run migrations");
+            // An encrypted database can't be read until the user enters its passphrase;
+            // the backup and migrations then run when it is unlocked
+            let readable = is_readable(&app.state::<Database>().conn.lock().unwrap());
+            if readable {
+                prepare_database(app.handle(), !is_new);
+            } else {
+                *app.state::<StartupState>().locked.lock().unwrap() = true;
             }
 
-            app.manage(database);
+            start_backup_scheduler(app.handle().clone());
+            start_sync_watcher(app.handle().clone());
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -32,20 +66,103 @@ pub fn run() {
             commands::create_plant,
             commands::update_plant,
             commands::delete_plant,
+            commands::resolve_plant,
+            commands::link_varieties_by_name,
             commands::get_plants_to_reorder,
+            commands::query_plants,
+            commands::get_plant_categories,
+            commands::create_plant_category,
+            commands::update_plant_category,
+            commands::reorder_plant_categories,
+            commands::delete_plant_category,
+            commands::get_tags,
+            commands::create_tag,
+            commands::update_tag,
+            commands::delete_tag,
+            commands::set_plant_tags,
+            commands::set_activity_tags,
+            commands::get_smart_lists,
+            commands::create_smart_list,
+            commands::run_smart_list,
+            commands::delete_smart_list,
+            commands::get_plant_tasks,
+            commands::create_plant_task,
+            commands::update_plant_task,
+            commands::complete_plant_task,
+            commands::delete_plant_task,
             commands::get_all_activities,
             commands::create_activity,
             commands::update_activity,
             commands::delete_activity,
+            commands::get_all_suppliers,
+            commands::create_supplier,
+            commands::update_supplier,
+            commands::delete_supplier,
             commands::get_month_data,
+            commands::get_bloom_calendar,
+            commands::get_journal_entries,
+            commands::get_journal_tags,
+            commands::create_journal_entry,
+            commands::update_journal_entry,
+            commands::delete_journal_entry,
+            commands::search,
             commands::get_photos,
+            commands::get_photo_timeline,
+            commands::get_photos_a_year_ago,
             commands::add_photo,
+            commands::import_photos,
             commands::delete_photo,
+            commands::get_photo_image,
+            commands::reorder_photos,
+            commands::update_photo,
+            commands::set_primary_photo,
+            commands::get_photo_settings,
+            commands::save_photo_settings,
+            commands::get_location,
+            commands::save_location,
             commands::import_plants_tsv,
             commands::get_database_path,
-            commands::save_database_path,
+            commands::integrity_check,
+            commands::get_startup_error,
+            commands::retry_migrations,
+            commands::get_schema_info,
+            commands::migrate_to,
+            commands::list_backups,
+            commands::backup_now,
+            commands::restore_backup,
+            commands::get_backup_retention,
+            commands::save_backup_retention,
+            commands::get_backup_schedule,
+            commands::save_backup_schedule,
+            commands::relocate_database,
+            commands::get_recent_databases,
+            commands::list_profiles,
+            commands::create_profile,
+            commands::switch_profile,
+            commands::delete_profile,
+            commands::get_encryption_status,
+            commands::is_database_locked,
+            commands::unlock_database,
+            commands::encrypt_database,
+            commands::decrypt_database,
+            commands::change_passphrase,
+            commands::get_sync_status,
+            commands::resolve_external_change,
+            commands::merge_database,
             commands::generate_pdf,
+            commands::generate_plant_sheet_pdf,
+            commands::get_shopping_list,
+            commands::get_shopping_list_text,
+            commands::export_shopping_list_csv,
+            commands::generate_shopping_list_pdf,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // Let other devices know the database is no longer in use
+            if let tauri::RunEvent::Exit = event {
+                let db = app.state::<Database>();
+                release_lease(&lease_path(&db.get_path()), &app.state::<SyncState>().instance_id);
+            }
+        });
 }
@@ -0,0 +1,202 @@
+use crate::models::Plant;
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+/// Average frost dates of the climate plant periods are entered for: seed packets and
+/// gardening books for the Netherlands. Periods are shifted by how far a garden differs from it.
+pub const REFERENCE_LAST_FROST: &str = "04-15";
+pub const REFERENCE_FIRST_FROST: &str = "10-25";
+
+const PERIODS: i32 = 24;
+const ALL_PERIODS: i32 = (1 << PERIODS) - 1;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Hemisphere {
+    #[default]
+    North,
+    South,
+}
+
+/// Where a garden is, per garden. Plants keep their periods for the reference climate; they're
+/// shifted to the garden's seasons when read and back when saved.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Location {
+    pub latitude: Option<f64>,
+    pub hemisphere: Hemisphere,
+    /// Average date of the last frost in spring, "MM-DD"
+    pub last_frost: Option<String>,
+    /// Average date of the first frost in autumn, "MM-DD"
+    pub first_frost: Option<String>,
+    /// Half-months to shift on top of the frost dates, e.g. 2 for a cold spot in the garden
+    pub adjust_periods: i32,
+}
+
+/// Position of a "MM-DD" date in the year, in half-months from the start of January.
+fn year_position(date: &str) -> Result<f64, String> {
+    // A year without February 29th, so every date falls in the same place
+    let parsed = NaiveDate::parse_from_str(&format!("2001-{}", date.trim()), "%Y-%m-%d")
+        .map_err(|_| format!("Invalid date: {}, expected month-day like 04-15", date))?;
+    Ok(parsed.ordinal0() as f64 * PERIODS as f64 / 365.0)
+}
+
+/// Half-months a garden's frost `date` falls after the `reference` one, the short way round the
+/// year. `hemisphere` half-months are taken off first, so southern dates compare with northern ones.
+fn periods_after(reference: &str, date: &str, hemisphere: i32) -> Result<f64, String> {
+    let periods = PERIODS as f64;
+    let difference = (year_position(date)? - hemisphere as f64 - year_position(reference)?).rem_euclid(periods);
+    Ok(if difference > periods / 2.0 { difference - periods } else { difference })
+}
+
+impl Location {
+    pub fn check(&self) -> Result<(), String> {
+        if let Some(latitude) = self.latitude {
+            if !(-90.0..=90.0).contains(&latitude) {
+                return Err("The latitude must be between -90 and 90".to_string());
+            }
+        }
+        for date in [&self.last_frost, &self.first_frost].into_iter().flatten() {
+            year_position(date)?;
+        }
+        Ok(())
+    }
+
+    /// Half-months to move the reference periods forward to get this garden's periods. The
+    /// southern hemisphere turns the year around by six months; later frost dates than the
+    /// reference climate mean a season that comes later. With both frost dates the season moves
+    /// by the average of how far each one moved. All periods move together, so periods saved in
+    /// the garden's seasons go back to the reference climate exactly.
+    pub fn period_shift(&self) -> Result<i32, String> {
+        let hemisphere = match self.hemisphere {
+            Hemisphere::North => 0,
+            Hemisphere::South => PERIODS / 2,
+        };
+        let mut frost_shifts = Vec::new();
+        if let Some(last_frost) = &self.last_frost {
+            frost_shifts.push(periods_after(REFERENCE_LAST_FROST, last_frost, hemisphere)?);
+        }
+        if let Some(first_frost) = &self.first_frost {
+            frost_shifts.push(periods_after(REFERENCE_FIRST_FROST, first_frost, hemisphere)?);
+        }
+        let frost = if frost_shifts.is_empty() {
+            0
+        } else {
+            (frost_shifts.iter().sum::<f64>() / frost_shifts.len() as f64).round() as i32
+        };
+        Ok((hemisphere + frost + self.adjust_periods).rem_euclid(PERIODS))
+    }
+}
+
+/// Move the periods in a half-month bitmask `shift` half-months later, wrapping around the end
+/// of the year. A negative shift moves them earlier.
+pub fn shift_periods(periods: i32, shift: i32) -> i32 {
+    let shift = shift.rem_euclid(PERIODS);
+    if shift == 0 {
+        return periods;
+    }
+    let periods = periods & ALL_PERIODS;
+    ((periods << shift) | (periods >> (PERIODS - shift))) & ALL_PERIODS
+}
+
+/// SQL expression for `column` shifted like `shift_periods`, for filtering and sorting in queries.
+pub fn shift_periods_sql(column: &str, shift: i32) -> String {
+    let shift = shift.rem_euclid(PERIODS);
+    if shift == 0 {
+        return column.to_string();
+    }
+    format!("((({0} << {1}) | ({0} >> {2})) & {3})", column, shift, PERIODS - shift, ALL_PERIODS)
+}
+
+/// Move a plant's sowing, planting and flowering periods by `shift` half-months.
+pub fn shift_plant(plant: &mut Plant, shift: i32) {
+    plant.sow_periods = shift_periods(plant.sow_periods, shift);
+    plant.plant_periods = shift_periods(plant.plant_periods, shift);
+    plant.bloom_periods = shift_periods(plant.bloom_periods, shift);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+
+    fn location(hemisphere: Hemisphere, last_frost: Option<&str>, first_frost: Option<&str>) -> Location {
+        Location {
+            hemisphere,
+            last_frost: last_frost.map(str::to_string),
+            first_frost: first_frost.map(str::to_string),
+            ..Location::default()
+        }
+    }
+
+    #[test]
+    fn the_reference_climate_does_not_shift() {
+        assert_eq!(Location::default().period_shift().unwrap(), 0);
+        let reference = location(Hemisphere::North, Some(REFERENCE_LAST_FROST), Some(REFERENCE_FIRST_FROST));
+        assert_eq!(reference.period_shift().unwrap(), 0);
+    }
+
+    #[test]
+    fn a_later_season_shifts_later() {
+        // Both frosts a month later
+        assert_eq!(location(Hemisphere::North, Some("05-15"), Some("11-25")).period_shift().unwrap(), 2);
+        // Both frosts a month earlier
+        assert_eq!(location(Hemisphere::North, Some("03-15"), Some("09-25")).period_shift().unwrap(), 22);
+        assert_eq!(location(Hemisphere::North, Some("05-15"), None).period_shift().unwrap(), 2);
+        assert_eq!(location(Hemisphere::North, None, Some("11-25")).period_shift().unwrap(), 2);
+        // A season that is a month shorter on both ends keeps its middle
+        assert_eq!(location(Hemisphere::North, Some("05-15"), Some("09-25")).period_shift().unwrap(), 0);
+    }
+
+    #[test]
+    fn the_southern_hemisphere_turns_the_year_around() {
+        assert_eq!(location(Hemisphere::South, None, None).period_shift().unwrap(), 12);
+        assert_eq!(location(Hemisphere::South, Some("10-15"), Some("04-25")).period_shift().unwrap(), 12);
+        assert_eq!(location(Hemisphere::South, Some("11-15"), Some("05-25")).period_shift().unwrap(), 14);
+    }
+
+    #[test]
+    fn adjustments_add_to_the_frost_dates() {
+        let mut cold_spot = location(Hemisphere::North, Some("05-15"), Some("11-25"));
+        cold_spot.adjust_periods = 1;
+        assert_eq!(cold_spot.period_shift().unwrap(), 3);
+        cold_spot.adjust_periods = -3;
+        assert_eq!(cold_spot.period_shift().unwrap(), 23);
+    }
+
+    #[test]
+    fn rejects_invalid_locations() {
+        assert!(location(Hemisphere::North, Some("april"), None).check().is_err());
+        assert!(location(Hemisphere::North, None, Some("02-30")).check().is_err());
+        let mut location = Location { latitude: Some(91.0), ..Location::default() };
+        assert!(location.check().is_err());
+        location.latitude = Some(-33.9);
+        assert!(location.check().is_ok());
+    }
+
+    #[test]
+    fn shifts_periods_around_the_year() {
+        assert_eq!(shift_periods(0b11, 2), 0b1100);
+        assert_eq!(shift_periods(0b1100, -2), 0b11);
+        // Late December wraps around to early January
+        assert_eq!(shift_periods(1 << 23, 1), 1);
+        assert_eq!(shift_periods(1, -1), 1 << 23);
+        assert_eq!(shift_periods(ALL_PERIODS, 7), ALL_PERIODS);
+        for shift in -PERIODS..=PERIODS {
+            assert_eq!(shift_periods(shift_periods(0b1011_0001, shift), -shift), 0b1011_0001);
+        }
+    }
+
+    #[test]
+    fn sql_shifts_periods_like_rust() {
+        let conn = Connection::open_in_memory().unwrap();
+        for periods in [0, 1, 0b11, 0b1011_0001, 1 << 23, 0b11 << 22, ALL_PERIODS] {
+            for shift in -PERIODS..=PERIODS {
+                let shifted: i32 = conn
+                    .query_row(&format!("SELECT {}", shift_periods_sql("?1", shift)), [periods], |row| row.get(0))
+                    .unwrap();
+                assert_eq!(shifted, shift_periods(periods, shift), "{:b} shifted {}", periods, shift);
+            }
+        }
+    }
+}
@@ -0,0 +1,683 @@
+use crate::db::{latest_schema_version, open_readable, run_migrations};
+use chrono::Local;
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// A table taking part in merges. Tables are listed parents first, so references to rows
+/// added from the other database can be mapped to their new ids.
+struct MergeTable {
+    name: &'static str,
+    /// Identifies the same row in both databases when their UUIDs differ, e.g. when both
+    /// copies were upgraded separately and each generated its own UUIDs. `{schema}` is replaced
+    /// by `main` or `other`.
+    match_key: &'static str,
+    /// Describes a row in the report. `{schema}` is replaced by `main` or `other`.
+    label: &'static str,
+    /// Columns holding the id of a row in another merged table
+    references: &'static [(&'static str, &'static str)],
+    /// Column holding the id of another row in the same table, like a variety's species. It is
+    /// mapped once all rows are merged, since a row can come before the row it refers to.
+    self_reference: Option<&'static str>,
+    /// Columns holding JSON with an array of ids of rows in another merged table at a path,
+    /// like the tags a smart list filters on: (column, path, table). Ids of rows that aren't
+    /// here are left out.
+    json_references: &'static [(&'static str, &'static str, &'static str)],
+}
+
+const MERGE_TABLES: &[MergeTable] = &[
+    MergeTable {
+        name: "suppliers",
+        match_key: "lower(trim(name))",
+        label: "name",
+        references: &[],
+        self_reference: None,
+        json_references: &[],
+    },
+    MergeTable {
+        // Plants refer to categories by key rather than id, so they need no mapping
+        name: "plant_categories",
+        match_key: "key",
+        label: "name",
+        references: &[],
+        self_reference: None,
+        json_references: &[],
+    },
+    MergeTable {
+        name: "tags",
+        match_key: "lower(trim(name))",
+        label: "name",
+        references: &[],
+        self_reference: None,
+        json_references: &[],
+    },
+    MergeTable {
+        name: "plants",
+        match_key: "lower(trim(name))",
+        label: "name",
+        references: &[("supplier_id", "suppliers")],
+        self_reference: Some("parent_id"),
+        json_references: &[],
+    },
+    MergeTable {
+        name: "activities",
+        match_key: "lower(trim(name))",
+        label: "name",
+        references: &[],
+        self_reference: None,
+        json_references: &[],
+    },
+    MergeTable {
+        // Parents are paired first and share their UUIDs from then on
+        name: "plant_tags",
+        match_key: "(SELECT uuid FROM {schema}.plants p WHERE p.id = plant_id) || ':' ||
+            (SELECT lower(trim(name)) FROM {schema}.tags t WHERE t.id = tag_id)",
+        label: "(SELECT name FROM {schema}.plants p WHERE p.id = plant_id) || ' #' ||
+            (SELECT name FROM {schema}.tags t WHERE t.id = tag_id)",
+        references: &[("plant_id", "plants"), ("tag_id", "tags")],
+        self_reference: None,
+        json_references: &[],
+    },
+    MergeTable {
+        name: "activity_tags",
+        match_key: "(SELECT uuid FROM {schema}.activities a WHERE a.id = activity_id) || ':' ||
+            (SELECT lower(trim(name)) FROM {schema}.tags t WHERE t.id = tag_id)",
+        label: "(SELECT name FROM {schema}.activities a WHERE a.id = activity_id) || ' #' ||
+            (SELECT name FROM {schema}.tags t WHERE t.id = tag_id)",
+        references: &[("activity_id", "activities"), ("tag_id", "tags")],
+        self_reference: None,
+        json_references: &[],
+    },
+    MergeTable {
+        name: "plant_tasks",
+        match_key: "(SELECT uuid FROM {schema}.plants p WHERE p.id = plant_id) || ':' || lower(trim(name))",
+        label: "(SELECT name FROM {schema}.plants p WHERE p.id = plant_id) || ': ' || name",
+        references: &[("plant_id", "plants")],
+        self_reference: None,
+        json_references: &[],
+    },
+    MergeTable {
+        name: "journal_entries",
+        match_key: "entry_date || ':' || text",
+        label: "entry_date || ' ' || substr(text, 1, 40)",
+        references: &[("plant_id", "plants"), ("activity_id", "activities")],
+        self_reference: None,
+        json_references: &[],
+    },
+    MergeTable {
+        name: "plant_photos",
+        match_key: "length(image_data) || ':' || hex(substr(image_data, 1, 512))",
+        label: "'Photo of ' || COALESCE(
+            (SELECT name FROM {schema}.plants p WHERE p.id = plant_id),
+            (SELECT name FROM {schema}.activities a WHERE a.id = activity_id),
+            (SELECT 'journal entry of ' || entry_date FROM {schema}.journal_entries j WHERE j.id = journal_entry_id),
+            (SELECT p.name || ': ' || t.name FROM {schema}.plant_tasks t JOIN {schema}.plants p ON p.id = t.plant_id WHERE t.id = plant_task_id)
+        )",
+        references: &[
+            ("plant_id", "plants"),
+            ("activity_id", "activities"),
+            ("journal_entry_id", "journal_entries"),
+            ("plant_task_id", "plant_tasks"),
+        ],
+        self_reference: None,
+        json_references: &[],
+    },
+    MergeTable {
+        name: "smart_lists",
+        match_key: "lower(trim(name))",
+        label: "name",
+        references: &[],
+        self_reference: None,
+        json_references: &[("filter", "$.tag_ids", "tags")],
+    },
+];
+
+#[derive(Debug, Serialize, Default)]
+pub struct TableMergeSummary {
+    pub table: String,
+    /// Rows copied from the other database
+    pub added: u32,
+    /// Rows replaced by a newer version from the other database
+    pub updated: u32,
+    /// Rows deleted here because they were deleted in the other database
+    pub deleted: u32,
+    /// Rows in the other database that were deleted here, or whose parent was
+    pub skipped: u32,
+}
+
+/// A row that differs between the two databases. The most recently modified version is kept.
+#[derive(Debug, Serialize)]
+pub struct MergeConflict {
+    pub table: String,
+    pub label: String,
+    /// "this" or "other"
+    pub kept: String,
+    pub this_updated_at: Option<String>,
+    pub other_updated_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Default)]
+pub struct MergeReport {
+    pub tables: Vec<TableMergeSummary>,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+struct RowInfo {
+    id: i64,
+    uuid: String,
+    key: Option<String>,
+    label: String,
+    updated_at: Option<String>,
+}
+
+fn table_columns(conn: &Connection, schema: &str, table: &str) -> rusqlite::Result<Vec<String>> {
+    let mut stmt = conn.prepare(&format!("SELECT name FROM pragma_table_info('{}', '{}')", table, schema))?;
+    let names = stmt.query_map([], |row| row.get(0))?.collect();
+    names
+}
+
+fn load_rows(conn: &Connection, schema: &str, table: &MergeTable, has_updated_at: bool) -> rusqlite::Result<Vec<RowInfo>> {
+    let sql = format!(
+        "SELECT id, uuid, {}, COALESCE({}, ''), {} FROM {}.{}",
+        table.match_key.replace("{schema}", schema),
+        table.label.replace("{schema}", schema),
+        if has_updated_at { "updated_at" } else { "NULL" },
+        schema,
+        table.name
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(RowInfo {
+                id: row.get(0)?,
+                uuid: row.get(1)?,
+                key: row.get(2)?,
+                label: row.get(3)?,
+                updated_at: row.get(4)?,
+            })
+        })?
+        .collect();
+    rows
+}
+
+fn tombstone(conn: &Connection, schema: &str, table: &str, uuid: &str) -> rusqlite::Result<Option<String>> {
+    conn.query_row(
+        &format!("SELECT deleted_at FROM {}.deleted_rows WHERE table_name = ?1 AND uuid = ?2", schema),
+        params![table, uuid],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+/// Whether a tombstone should win over a row last modified at `updated_at`.
+fn deletion_wins(deleted_at: &Option<String>, updated_at: &Option<String>) -> bool {
+    match (deleted_at, updated_at) {
+        (None, _) => false,
+        (Some(_), None) => true,
+        (Some(deleted_at), Some(updated_at)) => deleted_at >= updated_at,
+    }
+}
+
+type IdMap = HashMap<&'static str, HashMap<i64, i64>>;
+
+/// The values of a row's reference columns in the other database, mapped to ids in this one.
+/// Returns None if a referenced row doesn't exist here.
+fn mapped_references(conn: &Connection, table: &MergeTable, other_id: i64, id_map: &IdMap) -> rusqlite::Result<Option<Vec<Value>>> {
+    let mut mapped = Vec::new();
+    for (column, referenced_table) in table.references {
+        let value: Option<i64> = conn.query_row(
+            &format!("SELECT {} FROM other.{} WHERE id = ?1", column, table.name),
+            [other_id],
+            |row| row.get(0),
+        )?;
+        match value {
+            None => mapped.push(Value::Null),
+            Some(id) => match id_map.get(referenced_table).and_then(|ids| ids.get(&id)) {
+                Some(local_id) => mapped.push(Value::Integer(*local_id)),
+                None => return Ok(None),
+            },
+        }
+    }
+    Ok(Some(mapped))
+}
+
+/// The ids of `referenced_table`'s rows in the other database mapped to ids here, as a JSON
+/// object for `mapped_json_sql`.
+fn json_id_map(id_map: &IdMap, referenced_table: &str) -> Result<Value, String> {
+    let ids: HashMap<String, i64> = id_map
+        .get(referenced_table)
+        .map(|ids| ids.iter().map(|(other_id, id)| (other_id.to_string(), *id)).collect())
+        .unwrap_or_default();
+    serde_json::to_string(&ids).map(Value::Text).map_err(|e| e.to_string())
+}
+
+/// SQL expression for the JSON in `column` with the ids in the array at `path` mapped through
+/// the JSON object in parameter `map_param`. The rest of the JSON is left as it is.
+fn mapped_json_sql(column: &str, path: &str, map_param: usize) -> String {
+    format!(
+        "CASE WHEN json_type({0}, '{1}') = 'array' THEN json_set({0}, '{1}', json((
+            SELECT json_group_array(json_extract(?{2}, '$.\"' || value || '\"')) FROM json_each({0}, '{1}')
+            WHERE json_extract(?{2}, '$.\"' || value || '\"') IS NOT NULL
+        ))) ELSE {0} END",
+        column, path, map_param
+    )
+}
+
+/// Expression list selecting `columns` from the other database, with reference columns taken
+/// from numbered parameters starting at ?2 (?1 is the row id), followed by the id maps of the
+/// JSON references. The self reference is left empty until all rows are merged.
+fn select_list(columns: &[String], table: &MergeTable) -> String {
+    columns
+        .iter()
+        .map(|column| {
+            if let Some(index) = table.references.iter().position(|(c, _)| c == column) {
+                return format!("?{}", index + 2);
+            }
+            if let Some(index) = table.json_references.iter().position(|(c, _, _)| c == column) {
+                let (_, path, _) = table.json_references[index];
+                return mapped_json_sql(&format!("\"{}\"", column), path, table.references.len() + index + 2);
+            }
+            if table.self_reference == Some(column.as_str()) {
+                return "NULL".to_string();
+            }
+            format!("\"{}\"", column)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn merge_table(conn: &Connection, table: &MergeTable, id_map: &mut IdMap, report: &mut MergeReport) -> Result<(), String> {
+    let columns: Vec<String> = table_columns(conn, "main", table.name)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter(|c| c != "id")
+        .collect();
+    let mut other_columns = table_columns(conn, "other", table.name).map_err(|e| e.to_string())?;
+    other_columns.retain(|c| c != "id");
+    if other_columns.len() != columns.len() || !columns.iter().all(|c| other_columns.contains(c)) {
+        return Err(format!("The {} table differs between the two databases", table.name));
+    }
+    let has_updated_at = columns.iter().any(|c| c == "updated_at");
+    let json_maps = table
+        .json_references
+        .iter()
+        .map(|(_, _, referenced_table)| json_id_map(id_map, referenced_table))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let local = load_rows(conn, "main", table, has_updated_at).map_err(|e| e.to_string())?;
+    let other = load_rows(conn, "other", table, has_updated_at).map_err(|e| e.to_string())?;
+
+    // Pair rows by UUID, then pair what's left by match key when that is unambiguous
+    let local_by_uuid: HashMap<&str, usize> = local.iter().enumerate().map(|(i, r)| (r.uuid.as_str(), i)).collect();
+    let mut local_paired = vec![false; local.len()];
+    let mut pairs: Vec<(usize, usize)> = Vec::new();
+    let mut unpaired_other: Vec<usize> = Vec::new();
+    for (o, row) in other.iter().enumerate() {
+        match local_by_uuid.get(row.uuid.as_str()) {
+            Some(&l) => {
+                local_paired[l] = true;
+                pairs.push((l, o));
+            }
+            None => unpaired_other.push(o),
+        }
+    }
+
+    let mut local_by_key: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (l, row) in local.iter().enumerate() {
+        if let (false, Some(key)) = (local_paired[l], row.key.as_deref()) {
+            local_by_key.entry(key).or_default().push(l);
+        }
+    }
+    let mut other_key_counts: HashMap<&str, usize> = HashMap::new();
+    for &o in &unpaired_other {
+        if let Some(key) = other[o].key.as_deref() {
+            *other_key_counts.entry(key).or_default() += 1;
+        }
+    }
+    let mut added_other: Vec<usize> = Vec::new();
+    for &o in &unpaired_other {
+        let candidates = other[o].key.as_deref().and_then(|key| {
+            (other_key_counts[key] == 1).then(|| local_by_key.get(key)).flatten()
+        });
+        match candidates {
+            Some(candidates) if candidates.len() == 1 => {
+                let l = candidates[0];
+                local_paired[l] = true;
+                pairs.push((l, o));
+                // Adopt the other database's UUID so the rows pair up directly next time
+                conn.execute(&format!("UPDATE main.{} SET uuid = ?1 WHERE id = ?2", table.name), params![other[o].uuid, local[l].id])
+                    .map_err(|e| e.to_string())?;
+            }
+            _ => added_other.push(o),
+        }
+    }
+
+    let mut summary = TableMergeSummary {
+        table: table.name.to_string(),
+        ..Default::default()
+    };
+    // Ids of this table's rows in the other database, mapped to ids here
+    let mut table_ids: HashMap<i64, i64> = HashMap::new();
+    // Rows copied from the other database, whose self reference still has to be set
+    let mut copied: Vec<(i64, i64)> = Vec::new();
+
+    // Rows in both: compare the content, with references mapped, and keep the newest
+    let compared: Vec<&String> = columns
+        .iter()
+        .filter(|c| !matches!(c.as_str(), "uuid" | "created_at" | "updated_at"))
+        .filter(|c| !table.references.iter().any(|(r, _)| r == c))
+        .filter(|c| table.self_reference != Some(c.as_str()))
+        .filter(|c| !table.json_references.iter().any(|(r, _, _)| r == c))
+        .collect();
+    let mut differs_sql = format!(
+        "SELECT EXISTS(SELECT 1 FROM main.{0} l, other.{0} r WHERE l.id = ?1 AND r.id = ?2 AND (0",
+        table.name
+    );
+    for column in &compared {
+        differs_sql.push_str(&format!(" OR l.\"{0}\" IS NOT r.\"{0}\"", column));
+    }
+    for (index, (column, _)) in table.references.iter().enumerate() {
+        differs_sql.push_str(&format!(" OR l.\"{}\" IS NOT ?{}", column, index + 3));
+    }
+    for (index, (column, path, _)) in table.json_references.iter().enumerate() {
+        let mapped = mapped_json_sql(&format!("r.\"{}\"", column), path, table.references.len() + index + 3);
+        differs_sql.push_str(&format!(" OR l.\"{}\" IS NOT {}", column, mapped));
+    }
+    // Paired rows share their UUIDs by now, so the referenced rows can be compared by UUID
+    if let Some(column) = table.self_reference {
+        differs_sql.push_str(&format!(
+            " OR (SELECT uuid FROM main.{0} WHERE id = l.\"{1}\") IS NOT (SELECT uuid FROM other.{0} WHERE id = r.\"{1}\")",
+            table.name, column
+        ));
+    }
+    differs_sql.push_str("))");
+    let update_sql = format!(
+        "UPDATE main.{0} SET ({1}) = (SELECT {2} FROM other.{0} WHERE id = ?1) WHERE id = ?{3}",
+        table.name,
+        columns.iter().map(|c| format!("\"{}\"", c)).collect::<Vec<_>>().join(", "),
+        select_list(&columns, table),
+        table.references.len() + table.json_references.len() + 2
+    );
+
+    for (l, o) in pairs {
+        let (local_row, other_row) = (&local[l], &other[o]);
+        table_ids.insert(other_row.id, local_row.id);
+
+        // A reference to a row deleted here counts as no reference
+        let references = match mapped_references(conn, table, other_row.id, id_map).map_err(|e| e.to_string())? {
+            Some(references) => references,
+            None => table.references.iter().map(|_| Value::Null).collect(),
+        };
+
+        let mut differs_params = vec![Value::Integer(local_row.id), Value::Integer(other_row.id)];
+        differs_params.extend(references.iter().cloned());
+        differs_params.extend(json_maps.iter().cloned());
+        let differs: bool = conn
+            .query_row(&differs_sql, params_from_iter(differs_params), |row| row.get(0))
+            .map_err(|e| e.to_string())?;
+        // Without modification times (photos) there is nothing to decide on, so keep ours
+        if !differs || !has_updated_at {
+            continue;
+        }
+
+        let other_is_newer = other_row.updated_at > local_row.updated_at;
+        if other_is_newer {
+            let mut update_params = vec![Value::Integer(other_row.id)];
+            update_params.extend(references);
+            update_params.extend(json_maps.iter().cloned());
+            update_params.push(Value::Integer(local_row.id));
+            conn.execute(&update_sql, params_from_iter(update_params)).map_err(|e| e.to_string())?;
+            copied.push((other_row.id, local_row.id));
+            summary.updated += 1;
+        }
+        report.conflicts.push(MergeConflict {
+            table: table.name.to_string(),
+            label: local_row.label.clone(),
+            kept: if other_is_newer { "other" } else { "this" }.to_string(),
+            this_updated_at: local_row.updated_at.clone(),
+            other_updated_at: other_row.updated_at.clone(),
+        });
+    }
+
+    // Rows only in the other database: new there, unless we deleted them after their last change
+    let insert_sql = format!(
+        "INSERT INTO main.{0} ({1}) SELECT {2} FROM other.{0} WHERE id = ?1",
+        table.name,
+        columns.iter().map(|c| format!("\"{}\"", c)).collect::<Vec<_>>().join(", "),
+        select_list(&columns, table)
+    );
+    for o in added_other {
+        let other_row = &other[o];
+        let deleted_at = tombstone(conn, "main", table.name, &other_row.uuid).map_err(|e| e.to_string())?;
+        if deletion_wins(&deleted_at, &other_row.updated_at) {
+            summary.skipped += 1;
+            continue;
+        }
+        let Some(references) = mapped_references(conn, table, other_row.id, id_map).map_err(|e| e.to_string())? else {
+            // Its parent was deleted here
+            summary.skipped += 1;
+            continue;
+        };
+
+        let mut insert_params = vec![Value::Integer(other_row.id)];
+        insert_params.extend(references);
+        insert_params.extend(json_maps.iter().cloned());
+        conn.execute(&insert_sql, params_from_iter(insert_params)).map_err(|e| e.to_string())?;
+        table_ids.insert(other_row.id, conn.last_insert_rowid());
+        copied.push((other_row.id, conn.last_insert_rowid()));
+        summary.added += 1;
+    }
+
+    // Rows only here: new here, unless the other database deleted them after their last change
+    for (l, local_row) in local.iter().enumerate() {
+        if local_paired[l] {
+            continue;
+        }
+        let deleted_at = tombstone(conn, "other", table.name, &local_row.uuid).map_err(|e| e.to_string())?;
+        if deletion_wins(&deleted_at, &local_row.updated_at) {
+            conn.execute(&format!("DELETE FROM main.{} WHERE id = ?1", table.name), [local_row.id])
+                .map_err(|e| e.to_string())?;
+            summary.deleted += 1;
+        }
+    }
+
+    // A reference to a row that is not here after the merge counts as no reference
+    if let Some(column) = table.self_reference {
+        for (other_id, local_id) in copied {
+            let referenced: Option<i64> = conn
+                .query_row(&format!("SELECT \"{}\" FROM other.{} WHERE id = ?1", column, table.name), [other_id], |row| row.get(0))
+                .map_err(|e| e.to_string())?;
+            let mapped = referenced.and_then(|id| table_ids.get(&id).copied());
+            if mapped.is_some() {
+                conn.execute(&format!("UPDATE main.{} SET \"{}\" = ?1 WHERE id = ?2", table.name, column), params![mapped, local_id])
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+    }
+
+    id_map.insert(table.name, table_ids);
+    report.tables.push(summary);
+    Ok(())
+}
+
+/// Copy the other database to a temporary file and bring it to this app's schema version,
+/// so both sides have the same columns and the other file itself is never modified. Returns the
+/// copy and the passphrase it needs, if it is encrypted.
+fn prepare_other(other_path: &Path, key: Option<&str>) -> Result<(std::path::PathBuf, Option<String>), String> {
+    let temp_path = std::env::temp_dir().join(format!("tuinapp_merge_{}.db", Local::now().format("%Y%m%d%H%M%S")));
+    std::fs::copy(other_path, &temp_path).map_err(|e| format!("Failed to read {}: {}", other_path.display(), e))?;
+
+    let prepared = (|| {
+        let (mut conn, key) = open_readable(&temp_path, key)?;
+        let version: i32 = conn
+            .query_row("SELECT version FROM schema_version LIMIT 1", [], |row| row.get(0))
+            .map_err(|_| format!("{} is not a tuinapp database", other_path.display()))?;
+        if version > latest_schema_version() {
+            return Err("The other database is from a newer version of the app".to_string());
+        }
+        run_migrations(&mut conn)?;
+        Ok(key)
+    })();
+
+    match prepared {
+        Ok(key) => Ok((temp_path, key)),
+        Err(e) => {
+            let _ = std::fs::remove_file(&temp_path);
+            Err(e)
+        }
+    }
+}
+
+/// Merge another tuinapp database, such as a cloud "conflicted copy", into this one. Rows are
+/// paired by UUID; the most recently modified version of a row wins, and deletions on either
+/// side are applied when they happened after the row's last change. The other file is not
+/// changed. Everything happens in one transaction, so a failed merge leaves no trace.
+pub fn merge_database(conn: &mut Connection, other_path: &Path, key: Option<&str>) -> Result<MergeReport, String> {
+    let (temp_path, other_key) = prepare_other(other_path, key)?;
+
+    let result = (|| {
+        if cfg!(feature = "encryption") {
+            // Without a KEY clause SQLCipher would use this database's passphrase for the other one
+            conn.execute(
+                "ATTACH DATABASE ?1 AS other KEY ?2",
+                params![temp_path.to_string_lossy(), other_key.unwrap_or_default()],
+            )
+        } else {
+            conn.execute("ATTACH DATABASE ?1 AS other", [temp_path.to_string_lossy()])
+        }
+        .map_err(|e| e.to_string())?;
+
+        let merged = (|| {
+            let tx = conn.transaction().map_err(|e| e.to_string())?;
+            let mut report = MergeReport::default();
+            let mut id_map = IdMap::new();
+            for table in MERGE_TABLES {
+                merge_table(&tx, table, &mut id_map, &mut report)?;
+            }
+
+            // Carry over the other side's deletions so they reach databases merged with this one later
+            tx.execute_batch("INSERT OR IGNORE INTO main.deleted_rows SELECT table_name, uuid, deleted_at FROM other.deleted_rows;")
+                .map_err(|e| e.to_string())?;
+            tx.commit().map_err(|e| e.to_string())?;
+            Ok(report)
+        })();
+
+        let _ = conn.execute("DETACH DATABASE other", []);
+        merged
+    })();
+
+    let _ = std::fs::remove_file(&temp_path);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::open_connection;
+
+    fn names(conn: &Connection, sql: &str) -> Vec<String> {
+        let mut stmt = conn.prepare(sql).unwrap();
+        let rows = stmt.query_map([], |row| row.get(0)).unwrap();
+        rows.collect::<rusqlite::Result<Vec<String>>>().unwrap()
+    }
+
+    #[test]
+    fn deletion_wins_when_it_happened_after_the_last_change() {
+        let at = |time: &str| Some(time.to_string());
+        assert!(!deletion_wins(&None, &at("2026-01-01")));
+        assert!(deletion_wins(&at("2026-01-01"), &None));
+        assert!(deletion_wins(&at("2026-02-01"), &at("2026-01-01")));
+        assert!(!deletion_wins(&at("2026-01-01"), &at("2026-02-01")));
+    }
+
+    #[test]
+    fn merges_two_diverged_copies() {
+        let dir = std::env::temp_dir().join(format!("tuinapp-merge-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let this_path = dir.join("this.db");
+        let other_path = dir.join("other.db");
+
+        let mut conn = open_connection(&this_path, None).unwrap();
+        run_migrations(&mut conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO suppliers (name) VALUES ('Vreeken');
+             INSERT INTO plants (name, supplier_id, updated_at) VALUES
+                 ('Tomato', 1, '2020-01-01'), ('Lettuce', NULL, '2020-01-01'), ('Bean', NULL, '2020-01-01');
+             INSERT INTO tags (name) VALUES ('Greenhouse');
+             INSERT INTO smart_lists (name, filter) VALUES ('Under glass', json_object('tag_ids', json_array(1)));",
+        )
+        .unwrap();
+        std::fs::copy(&this_path, &other_path).unwrap();
+
+        conn.execute_batch(
+            "UPDATE plants SET notes = 'this, older', updated_at = '2021-01-01' WHERE name = 'Tomato';
+             UPDATE plants SET notes = 'this, newer', updated_at = '2023-01-01' WHERE name = 'Lettuce';
+             INSERT INTO plants (name) VALUES ('Carrot');
+             INSERT INTO tags (name) VALUES ('Shade');",
+        )
+        .unwrap();
+        let other = open_connection(&other_path, None).unwrap();
+        other
+            .execute_batch(
+                "UPDATE plants SET notes = 'other, newer', updated_at = '2022-01-01' WHERE name = 'Tomato';
+                 UPDATE plants SET notes = 'other, older', updated_at = '2022-06-01' WHERE name = 'Lettuce';
+                 DELETE FROM plants WHERE name = 'Bean';
+                 INSERT INTO suppliers (name) VALUES ('De Bolster');
+                 INSERT INTO plants (name, supplier_id) VALUES ('Pumpkin', 2);
+                 INSERT INTO plant_tasks (plant_id, name, periods) VALUES (1, 'Stake', 4096);
+                 INSERT INTO journal_entries (entry_date, text) VALUES ('2026-05-01', 'Slugs in the lettuce');
+                 INSERT INTO plant_photos (journal_entry_id, image_data) VALUES (1, x'0102');
+                 INSERT INTO tags (name) VALUES ('Herbs'), ('Shade');
+                 INSERT INTO smart_lists (name, filter) VALUES ('Shade herbs', json_object('plant_types', json_array('herb'), 'tag_ids', json_array(3, 2, 99)));",
+            )
+            .unwrap();
+        drop(other);
+
+        let report = merge_database(&mut conn, &other_path, None).unwrap();
+        let plants = report.tables.iter().find(|summary| summary.table == "plants").unwrap();
+        assert_eq!((plants.added, plants.updated, plants.deleted), (1, 1, 1));
+        assert_eq!(report.conflicts.len(), 2);
+
+        assert_eq!(
+            names(&conn, "SELECT name || ': ' || COALESCE(notes, '') FROM plants ORDER BY name"),
+            ["Carrot: ", "Lettuce: this, newer", "Pumpkin: ", "Tomato: other, newer"]
+        );
+        // References are mapped to the ids of this database
+        assert_eq!(
+            names(&conn, "SELECT s.name FROM plants p JOIN suppliers s ON s.id = p.supplier_id ORDER BY p.name"),
+            ["De Bolster", "Vreeken"]
+        );
+        assert_eq!(
+            names(&conn, "SELECT p.name || ': ' || t.name FROM plant_tasks t JOIN plants p ON p.id = t.plant_id"),
+            ["Tomato: Stake"]
+        );
+        assert_eq!(
+            names(&conn, "SELECT j.text FROM plant_photos p JOIN journal_entries j ON j.id = p.journal_entry_id"),
+            ["Slugs in the lettuce"]
+        );
+        // Smart lists filter on the same tags, and leave out tags that aren't here
+        assert_eq!(
+            names(
+                &conn,
+                "SELECT t.name FROM smart_lists s, json_each(s.filter, '$.tag_ids') j JOIN tags t ON t.id = j.value
+                 WHERE s.name = 'Shade herbs' ORDER BY j.key"
+            ),
+            ["Shade", "Herbs"]
+        );
+        assert_eq!(
+            names(&conn, "SELECT filter FROM smart_lists ORDER BY name"),
+            [r#"{"plant_types":["herb"],"tag_ids":[2,3]}"#, r#"{"tag_ids":[1]}"#]
+        );
+        let violations: i64 = conn.query_row("SELECT COUNT(*) FROM pragma_foreign_key_check", [], |row| row.get(0)).unwrap();
+        assert_eq!(violations, 0);
+
+        // Merging the same copy again changes nothing
+        let again = merge_database(&mut conn, &other_path, None).unwrap();
+        assert!(again.tables.iter().all(|s| s.added + s.updated + s.deleted == 0));
+
+        drop(conn);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -10,16 +10,76 @@ pub struct Plant {
     pub plant_periods: i32,
     pub notes: Option<String>,
     pub needs_reorder: bool,
+    pub supplier_id: Option<i64>,
+    #[serde(default = "default_packets_needed")]
+    pub packets_needed: i32,
+    pub last_price: Option<f64>,
     pub created_at: Option<String>,
     pub updated_at: Option<String>,
+    pub scientific_name: Option<String>,
+    pub cultivar: Option<String>,
+    pub family: Option<String>,
+    /// `annual`, `biennial` or `perennial`
+    pub lifecycle: Option<String>,
+    /// Free text, e.g. a USDA zone like "7b" or an RHS rating like "H4"
+    pub hardiness_zone: Option<String>,
+    // Depths, spacings and sizes in centimetres
+    pub sowing_depth_cm: Option<f64>,
+    pub in_row_spacing_cm: Option<f64>,
+    pub row_spacing_cm: Option<f64>,
+    pub mature_height_cm: Option<f64>,
+    pub mature_spread_cm: Option<f64>,
+    /// `low`, `medium` or `high`
+    pub water_needs: Option<String>,
+    /// The species this plant is a variety of
+    pub parent_id: Option<i64>,
+    /// Half-month periods in which the plant flowers, like `sow_periods`
+    pub bloom_periods: i32,
+    pub flower_color: Option<String>,
+    /// Year the plant went into the garden, the start for counting recurring tasks
+    pub established_year: Option<i32>,
+    /// Ids of the plant's tags. Left unchanged when saving without them.
+    pub tag_ids: Option<Vec<i64>>,
+    /// Fields a variety takes from its species and keeps following. Left unchanged when saving
+    /// without them, except that a plant that becomes a variety inherits what it leaves empty.
+    pub inherited_fields: Option<Vec<String>>,
 }
 
+fn default_packets_needed() -> i32 {
+    1
+}
+
+/// Recurring care for a plant, like dividing a perennial every three years. Due in its periods
+/// once `interval_years` have passed since it was last done.
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct PlantPhoto {
+pub struct PlantTask {
     pub id: Option<i64>,
     pub plant_id: i64,
+    pub name: String,
+    pub periods: i32,
+    pub interval_years: i32,
+    pub last_done_year: Option<i32>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PlantPhoto {
+    pub id: Option<i64>,
+    /// What the photo belongs to; exactly one is set
+    pub plant_id: Option<i64>,
+    pub activity_id: Option<i64>,
+    pub journal_entry_id: Option<i64>,
+    pub plant_task_id: Option<i64>,
     pub sort_order: i32,
-    pub image_data: Option<String>, // base64 encoded
+    pub thumbnail: Option<String>, // base64 encoded JPEG
+    /// Whether the original file is kept next to the database
+    pub has_original: bool,
+    pub caption: Option<String>,
+    /// When the photo was taken, from EXIF data or entered by hand
+    pub taken_at: Option<String>,
+    /// Shown as the plant's avatar in lists and first on plant sheets
+    pub is_primary: bool,
     pub created_at: Option<String>,
 }
 
@@ -31,4 +91,56 @@ pub struct Activity {
     pub active_periods: i32,
     pub created_at: Option<String>,
     pub updated_at: Option<String>,
+    /// Ids of the activity's tags. Left unchanged when saving without them.
+    pub tag_ids: Option<Vec<i64>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Tag {
+    pub id: Option<i64>,
+    pub name: String,
+    /// CSS colour, e.g. "#607d8b"
+    pub color: String,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+/// A user-defined plant category. Plants refer to it by `key` in their `plant_type`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PlantCategory {
+    pub id: Option<i64>,
+    /// Stable identifier derived from the name when the category is created
+    #[serde(default)]
+    pub key: String,
+    pub name: String,
+    /// CSS colour, e.g. "#4caf50"
+    pub color: String,
+    pub icon: Option<String>,
+    pub sort_order: i32,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JournalEntry {
+    pub id: Option<i64>,
+    /// The day the entry is about, as YYYY-MM-DD
+    pub entry_date: String,
+    pub text: String,
+    pub tags: Vec<String>,
+    pub plant_id: Option<i64>,
+    pub activity_id: Option<i64>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Supplier {
+    pub id: Option<i64>,
+    pub name: String,
+    pub website: Option<String>,
+    pub email: Option<String>,
+    pub notes: Option<String>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
 }
@@ -0,0 +1,466 @@
+use image::{imageops::FilterType, DynamicImage, ImageFormat, ImageOutputFormat};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+/// Stored photos are downscaled to fit within this many pixels on their longest side
+const MAX_PHOTO_DIMENSION: u32 = 2048;
+/// Longest side of the thumbnails shown in lists and photo grids
+const THUMBNAIL_DIMENSION: u32 = 320;
+const PHOTO_QUALITY: u8 = 85;
+const THUMBNAIL_QUALITY: u8 = 75;
+/// Photos whose perceptual hashes differ in at most this many bits are considered the same
+const NEAR_DUPLICATE_DISTANCE: u32 = 6;
+
+/// What a photo belongs to. Sent from the frontend as `{ "type": "plant", "id": 1 }`.
+/// Sowing and other garden work are activities or plant tasks. There are no beds as such, so a
+/// photo of a bed goes with a journal entry.
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(tag = "type", content = "id", rename_all = "snake_case")]
+pub enum PhotoOwner {
+    Plant(i64),
+    Activity(i64),
+    JournalEntry(i64),
+    PlantTask(i64),
+}
+
+impl PhotoOwner {
+    /// Column in `plant_photos` referring to the owner
+    pub fn column(&self) -> &'static str {
+        match self {
+            PhotoOwner::Plant(_) => "plant_id",
+            PhotoOwner::Activity(_) => "activity_id",
+            PhotoOwner::JournalEntry(_) => "journal_entry_id",
+            PhotoOwner::PlantTask(_) => "plant_task_id",
+        }
+    }
+
+    pub fn id(&self) -> i64 {
+        match self {
+            PhotoOwner::Plant(id) | PhotoOwner::Activity(id) | PhotoOwner::JournalEntry(id) | PhotoOwner::PlantTask(id) => *id,
+        }
+    }
+}
+
+/// How photos are stored, per garden.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct PhotoSettings {
+    /// Also keep the file as it came from the camera, in a folder next to the database
+    pub keep_originals: bool,
+}
+
+/// A photo as it is stored in the database: a downscaled JPEG, its thumbnail and a
+/// perceptual hash to recognise near duplicates.
+pub struct ProcessedPhoto {
+    pub image: Vec<u8>,
+    pub thumbnail: Vec<u8>,
+    pub perceptual_hash: i64,
+}
+
+fn encode_jpeg(image: &DynamicImage, quality: u8) -> Result<Vec<u8>, String> {
+    // JPEG has no alpha channel
+    let rgb = DynamicImage::ImageRgb8(image.to_rgb8());
+    let mut bytes = Vec::new();
+    rgb.write_to(&mut Cursor::new(&mut bytes), ImageOutputFormat::Jpeg(quality))
+        .map_err(|e| format!("Failed to encode photo: {}", e))?;
+    Ok(bytes)
+}
+
+/// Downscale and re-encode a photo for storage, and make its thumbnail. The photo is turned
+/// upright according to its EXIF orientation and its metadata, including any GPS location, is
+/// dropped. An upright JPEG that is already small enough is kept instead of being re-compressed.
+pub fn process_photo(bytes: &[u8]) -> Result<ProcessedPhoto, String> {
+    let orientation = exif_orientation(bytes);
+    let decoded = image::load_from_memory(bytes).map_err(|e| format!("Unsupported image: {}", e))?;
+    let decoded = apply_orientation(decoded, orientation);
+
+    let fits = decoded.width() <= MAX_PHOTO_DIMENSION && decoded.height() <= MAX_PHOTO_DIMENSION;
+    let image = if fits && orientation == 1 && image::guess_format(bytes).ok() == Some(ImageFormat::Jpeg) {
+        strip_metadata(bytes)?
+    } else if fits {
+        encode_jpeg(&decoded, PHOTO_QUALITY)?
+    } else {
+        encode_jpeg(
+            &decoded.resize(MAX_PHOTO_DIMENSION, MAX_PHOTO_DIMENSION, FilterType::Lanczos3),
+            PHOTO_QUALITY,
+        )?
+    };
+
+    let thumbnail = encode_jpeg(
+        &decoded.resize(THUMBNAIL_DIMENSION, THUMBNAIL_DIMENSION, FilterType::Triangle),
+        THUMBNAIL_QUALITY,
+    )?;
+
+    Ok(ProcessedPhoto {
+        image,
+        thumbnail,
+        perceptual_hash: difference_hash(&decoded),
+    })
+}
+
+/// SHA-256 of a file, as hex. Used to name original files and to spot exact duplicates.
+pub fn content_hash(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 64-bit difference hash: compares the brightness of neighbouring pixels in a 9x8 grayscale
+/// version of the image, so it survives resizing and re-compression.
+fn difference_hash(image: &DynamicImage) -> i64 {
+    let small = image.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    let mut hash: u64 = 0;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash as i64
+}
+
+pub fn is_near_duplicate(hash: i64, other: i64) -> bool {
+    (hash ^ other).count_ones() <= NEAR_DUPLICATE_DISTANCE
+}
+
+/// EXIF orientation from 1 to 8, 1 meaning the image is stored upright.
+fn exif_orientation(bytes: &[u8]) -> u32 {
+    exif::Reader::new()
+        .read_from_container(&mut Cursor::new(bytes))
+        .ok()
+        .and_then(|exif| exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY)?.value.get_uint(0))
+        .unwrap_or(1)
+}
+
+fn apply_orientation(image: DynamicImage, orientation: u32) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image,
+    }
+}
+
+/// Remove EXIF and XMP metadata from a JPEG (APP1 segments) or PNG (eXIf chunks) without
+/// touching the image data. Files that can't be parsed that way, that still carry EXIF data
+/// afterwards or that are stored rotated are decoded and re-encoded instead, which drops all
+/// metadata and turns the pixels upright.
+pub fn strip_metadata(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let stripped = match image::guess_format(bytes) {
+        Ok(ImageFormat::Jpeg) => strip_jpeg_metadata(bytes),
+        Ok(ImageFormat::Png) => strip_png_metadata(bytes),
+        _ => None,
+    };
+    match stripped {
+        // The orientation goes with the rest of the metadata, so rotated photos need their pixels turned
+        Some(stripped) if !has_exif(&stripped) && exif_orientation(bytes) == 1 => Ok(stripped),
+        _ => {
+            let decoded = image::load_from_memory(bytes).map_err(|e| format!("Unsupported image: {}", e))?;
+            encode_jpeg(&apply_orientation(decoded, exif_orientation(bytes)), PHOTO_QUALITY)
+        }
+    }
+}
+
+fn has_exif(bytes: &[u8]) -> bool {
+    exif::Reader::new().read_from_container(&mut Cursor::new(bytes)).is_ok()
+}
+
+fn strip_jpeg_metadata(bytes: &[u8]) -> Option<Vec<u8>> {
+    let mut output = bytes[..2].to_vec();
+    let mut pos = 2;
+    loop {
+        if *bytes.get(pos)? != 0xFF {
+            return None;
+        }
+        // Markers may be padded with any number of 0xFF fill bytes, which are dropped
+        while *bytes.get(pos + 1)? == 0xFF {
+            pos += 1;
+        }
+        let marker = bytes[pos + 1];
+        match marker {
+            // Start of scan: the compressed image data follows up to the end of the file
+            0xDA => {
+                output.extend_from_slice(&bytes[pos..]);
+                return Some(output);
+            }
+            // Standalone markers have no length or data
+            0x01 | 0xD0..=0xD7 => {
+                output.extend_from_slice(&bytes[pos..pos + 2]);
+                pos += 2;
+                continue;
+            }
+            _ => {}
+        }
+        let length = u16::from_be_bytes([*bytes.get(pos + 2)?, *bytes.get(pos + 3)?]) as usize;
+        let end = pos + 2 + length;
+        if length < 2 || end > bytes.len() {
+            return None;
+        }
+        if marker != 0xE1 {
+            output.extend_from_slice(&bytes[pos..end]);
+        }
+        pos = end;
+    }
+}
+
+fn strip_png_metadata(bytes: &[u8]) -> Option<Vec<u8>> {
+    let mut output = bytes[..8].to_vec();
+    let mut pos = 8;
+    while pos < bytes.len() {
+        let length = u32::from_be_bytes(bytes.get(pos..pos + 4)?.try_into().ok()?) as usize;
+        // Length, type, data and CRC
+        let end = pos + 12 + length;
+        if end > bytes.len() {
+            return None;
+        }
+        if &bytes[pos + 4..pos + 8] != b"eXIf" {
+            output.extend_from_slice(&bytes[pos..end]);
+        }
+        pos = end;
+    }
+    Some(output)
+}
+
+pub fn mime_type(bytes: &[u8]) -> &'static str {
+    image::guess_format(bytes)
+        .map(|format| format.to_mime_type())
+        .unwrap_or("image/jpeg")
+}
+
+/// When the photo was taken, from the EXIF `DateTimeOriginal` tag, as "YYYY-MM-DD HH:MM:SS".
+pub fn exif_taken_at(bytes: &[u8]) -> Option<String> {
+    let exif = exif::Reader::new().read_from_container(&mut Cursor::new(bytes)).ok()?;
+    let field = exif
+        .get_field(exif::Tag::DateTimeOriginal, exif::In::PRIMARY)
+        .or_else(|| exif.get_field(exif::Tag::DateTime, exif::In::PRIMARY))?;
+    let exif::Value::Ascii(ref values) = field.value else {
+        return None;
+    };
+    let taken_at = exif::DateTime::from_ascii(values.first()?).ok()?.to_string();
+
+    // Cameras with an unset clock write out-of-range values
+    chrono::NaiveDateTime::parse_from_str(&taken_at, "%Y-%m-%d %H:%M:%S").ok()?;
+    Some(taken_at)
+}
+
+/// Generate thumbnails and perceptual hashes for photos that were stored before they
+/// existed, downscaling the stored image at the same time. Photos that can't be decoded
+/// are left alone.
+pub fn backfill_photos(conn: &Connection, owner: PhotoOwner) -> rusqlite::Result<()> {
+    let pending = conn
+        .prepare(&format!(
+            "SELECT id, image_data FROM plant_photos
+             WHERE {} = ?1 AND (thumbnail IS NULL OR perceptual_hash IS NULL) AND image_data IS NOT NULL",
+            owner.column()
+        ))?
+        .query_map([owner.id()], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, Vec<u8>>(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    for (id, bytes) in pending {
+        if let Ok(photo) = process_photo(&bytes) {
+            conn.execute(
+                "UPDATE plant_photos SET image_data = ?1, thumbnail = ?2, perceptual_hash = ?3 WHERE id = ?4",
+                params![photo.image, photo.thumbnail, photo.perceptual_hash, id],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Folder next to the database holding original photo files, named by their SHA-256 hash so
+/// the same file is only stored once.
+pub fn originals_dir(db_path: &Path) -> PathBuf {
+    db_path.with_extension("originals")
+}
+
+pub fn original_path(db_path: &Path, file_name: &str) -> PathBuf {
+    originals_dir(db_path).join(&file_name[..2]).join(file_name)
+}
+
+/// Store an original photo file and return its file name.
+pub fn store_original(db_path: &Path, bytes: &[u8]) -> Result<String, String> {
+    let hash = content_hash(bytes);
+    let extension = image::guess_format(bytes)
+        .ok()
+        .and_then(|format| format.extensions_str().first().copied())
+        .unwrap_or("bin");
+    let file_name = format!("{}.{}", hash, extension);
+
+    let path = original_path(db_path, &file_name);
+    if !path.exists() {
+        let dir = path.parent().expect("original path has a parent");
+        std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        let partial_path = path.with_extension("partial");
+        std::fs::write(&partial_path, bytes)
+            .and_then(|_| std::fs::rename(&partial_path, &path))
+            .map_err(|e| format!("Failed to store original photo: {}", e))?;
+    }
+    Ok(file_name)
+}
+
+/// Delete original files that no photo in the database refers to anymore.
+pub fn remove_unused_originals(conn: &Connection, db_path: &Path, file_names: &[String]) -> rusqlite::Result<()> {
+    for file_name in file_names {
+        let in_use: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM plant_photos WHERE original_file = ?1)",
+            [file_name],
+            |row| row.get(0),
+        )?;
+        if !in_use {
+            let _ = std::fs::remove_file(original_path(db_path, file_name));
+        }
+    }
+    Ok(())
+}
+
+/// Copy the originals folder along when the database is moved to `new_db_path`.
+pub fn copy_originals(db_path: &Path, new_db_path: &Path) -> Result<(), String> {
+    fn copy_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+        std::fs::create_dir_all(to)?;
+        for entry in std::fs::read_dir(from)? {
+            let entry = entry?;
+            let target = to.join(entry.file_name());
+            if entry.file_type()?.is_dir() {
+                copy_dir(&entry.path(), &target)?;
+            } else if !target.exists() {
+                std::fs::copy(entry.path(), &target)?;
+            }
+        }
+        Ok(())
+    }
+
+    let from = originals_dir(db_path);
+    if !from.is_dir() {
+        return Ok(());
+    }
+    copy_dir(&from, &originals_dir(new_db_path)).map_err(|e| format!("Failed to copy original photos: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exif::experimental::Writer;
+    use exif::{Field, In, Rational, Tag, Value};
+
+    fn gradient(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgb8(image::RgbImage::from_fn(width, height, |x, y| {
+            image::Rgb([(x * 8) as u8, (y * 8) as u8, 128])
+        }))
+    }
+
+    /// A JPEG with an EXIF segment holding a GPS location, right after the start of image marker.
+    fn jpeg_with_gps(image: &DynamicImage) -> Vec<u8> {
+        jpeg_with_exif(image, 1)
+    }
+
+    /// Like `jpeg_with_gps`, stored with EXIF `orientation`.
+    fn jpeg_with_exif(image: &DynamicImage, orientation: u16) -> Vec<u8> {
+        let latitude_ref = Field {
+            tag: Tag::GPSLatitudeRef,
+            ifd_num: In::PRIMARY,
+            value: Value::Ascii(vec![b"N".to_vec()]),
+        };
+        let latitude = Field {
+            tag: Tag::GPSLatitude,
+            ifd_num: In::PRIMARY,
+            value: Value::Rational(vec![Rational::from((52, 1)), Rational::from((5, 1)), Rational::from((0, 1))]),
+        };
+        let orientation = Field {
+            tag: Tag::Orientation,
+            ifd_num: In::PRIMARY,
+            value: Value::Short(vec![orientation]),
+        };
+        let mut writer = Writer::new();
+        writer.push_field(&latitude_ref);
+        writer.push_field(&latitude);
+        writer.push_field(&orientation);
+        let mut tiff = Cursor::new(Vec::new());
+        writer.write(&mut tiff, false).unwrap();
+
+        let mut segment = b"Exif\0\0".to_vec();
+        segment.extend_from_slice(&tiff.into_inner());
+        let jpeg = encode_jpeg(image, PHOTO_QUALITY).unwrap();
+        let mut bytes = jpeg[..2].to_vec();
+        bytes.extend_from_slice(&[0xFF, 0xE1]);
+        bytes.extend_from_slice(&(segment.len() as u16 + 2).to_be_bytes());
+        bytes.extend_from_slice(&segment);
+        bytes.extend_from_slice(&jpeg[2..]);
+        bytes
+    }
+
+    fn has_gps(bytes: &[u8]) -> bool {
+        exif::Reader::new()
+            .read_from_container(&mut Cursor::new(bytes))
+            .map(|exif| exif.get_field(Tag::GPSLatitude, In::PRIMARY).is_some())
+            .unwrap_or(false)
+    }
+
+    #[test]
+    fn strips_gps_from_jpegs() {
+        let bytes = jpeg_with_gps(&gradient(32, 24));
+        assert!(has_gps(&bytes));
+
+        let stripped = strip_metadata(&bytes).unwrap();
+        assert!(!has_gps(&stripped));
+        // Removed from the file as it is, without re-encoding
+        assert_eq!(strip_jpeg_metadata(&bytes), Some(stripped));
+    }
+
+    #[test]
+    fn strips_gps_behind_fill_bytes() {
+        let mut bytes = jpeg_with_gps(&gradient(32, 24));
+        bytes.splice(2..2, [0xFF, 0xFF]);
+        let stripped = strip_jpeg_metadata(&bytes).unwrap();
+        assert!(!has_gps(&stripped));
+        assert_eq!(strip_metadata(&bytes).unwrap(), stripped);
+    }
+
+    #[test]
+    fn reencodes_jpegs_it_cannot_parse() {
+        let mut bytes = jpeg_with_gps(&gradient(32, 24));
+        // A stray byte between segments, which decoders skip but the segment walk can't
+        let app1_end = 4 + u16::from_be_bytes([bytes[4], bytes[5]]) as usize;
+        bytes.insert(app1_end, 0);
+        assert!(strip_jpeg_metadata(&bytes).is_none());
+
+        let stripped = strip_metadata(&bytes).unwrap();
+        assert!(!has_gps(&stripped));
+        let decoded = image::load_from_memory(&stripped).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (32, 24));
+    }
+
+    #[test]
+    fn turns_rotated_photos_upright() {
+        let image = gradient(32, 24);
+        let bytes = jpeg_with_exif(&image, 6);
+        assert_eq!(exif_orientation(&bytes), 6);
+
+        let stripped = strip_metadata(&bytes).unwrap();
+        assert!(!has_exif(&stripped));
+        let decoded = image::load_from_memory(&stripped).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (24, 32));
+        assert!(is_near_duplicate(difference_hash(&decoded), difference_hash(&image.rotate90())));
+    }
+
+    #[test]
+    fn processed_photos_have_no_gps() {
+        let photo = process_photo(&jpeg_with_gps(&gradient(32, 24))).unwrap();
+        assert!(!has_gps(&photo.image));
+        assert!(!has_gps(&photo.thumbnail));
+    }
+
+    #[test]
+    fn near_duplicates_survive_reencoding() {
+        let image = gradient(64, 48);
+        let reencoded = image::load_from_memory(&encode_jpeg(&image, 50).unwrap()).unwrap();
+        assert!(is_near_duplicate(difference_hash(&image), difference_hash(&reencoded)));
+        assert!(!is_near_duplicate(difference_hash(&image), difference_hash(&image.fliph())));
+    }
+}
@@ -0,0 +1,180 @@
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+// Control characters around matched terms in FTS5 highlights. They can't occur in text typed
+// into the app, so the highlighted text can be split without escaping.
+const MATCH_START: char = '\u{2}';
+const MATCH_END: char = '\u{3}';
+
+/// Words that make up the body snippet of a result
+const SNIPPET_TOKENS: i32 = 16;
+
+/// A piece of highlighted text: either a matched term or the text around it
+#[derive(Debug, Serialize)]
+pub struct TextSegment {
+    pub text: String,
+    pub matched: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchResult {
+    /// `plant`, `activity` or `journal`
+    pub entity_type: String,
+    pub entity_id: i64,
+    pub title: Vec<TextSegment>,
+    pub snippet: Vec<TextSegment>,
+    /// BM25 score, lower is a better match
+    pub rank: f64,
+}
+
+/// Turn what the user typed into an FTS5 query: every word must match, as a prefix so results
+/// show up while typing. Words are quoted so FTS5 operators and punctuation are taken literally.
+pub fn fts_query(input: &str) -> Option<String> {
+    let terms: Vec<String> = input
+        .split_whitespace()
+        .map(|word| word.replace('"', ""))
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("\"{}\"*", word))
+        .collect();
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
+/// Split FTS5 `highlight()`/`snippet()` output at the match markers.
+fn split_highlights(text: &str) -> Vec<TextSegment> {
+    let mut segments = Vec::new();
+    let mut current = String::new();
+    let mut matched = false;
+    for c in text.chars() {
+        if c == MATCH_START || c == MATCH_END {
+            if !current.is_empty() {
+                segments.push(TextSegment { text: std::mem::take(&mut current), matched });
+            }
+            matched = c == MATCH_START;
+        } else {
+            current.push(c);
+        }
+    }
+    if !current.is_empty() {
+        segments.push(TextSegment { text: current, matched });
+    }
+    segments
+}
+
+/// Search names, notes, descriptions and journal text, best matches first. Names weigh
+/// more than the text below them.
+pub fn search(conn: &Connection, input: &str, limit: usize) -> rusqlite::Result<Vec<SearchResult>> {
+    let Some(query) = fts_query(input) else {
+        return Ok(Vec::new());
+    };
+    let mut stmt = conn.prepare(
+        "SELECT entity_type, entity_id,
+                highlight(search_index, 2, ?2, ?3),
+                snippet(search_index, 3, ?2, ?3, '…', ?4),
+                bm25(search_index, 0.0, 0.0, 10.0, 1.0) AS rank
+         FROM search_index
+         WHERE search_index MATCH ?1
+         ORDER BY rank
+         LIMIT ?5",
+    )?;
+    let results = stmt
+        .query_map(
+            params![
+                query,
+                MATCH_START.to_string(),
+                MATCH_END.to_string(),
+                SNIPPET_TOKENS,
+                limit as i64
+            ],
+            |row| {
+                let title: String = row.get(2)?;
+                let snippet: String = row.get(3)?;
+                Ok(SearchResult {
+                    entity_type: row.get(0)?,
+                    entity_id: row.get(1)?,
+                    title: split_highlights(&title),
+                    snippet: split_highlights(&snippet),
+                    rank: row.get(4)?,
+                })
+            },
+        )?
+        .collect();
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{open_connection, run_migrations};
+
+    fn database() -> Connection {
+        let mut conn = open_connection(&std::path::PathBuf::from(":memory:"), None).unwrap();
+        run_migrations(&mut conn).unwrap();
+        conn
+    }
+
+    /// `entity_type:entity_id` of the results for `input`, best match first
+    fn found(conn: &Connection, input: &str) -> Vec<String> {
+        search(conn, input, 10)
+            .unwrap()
+            .iter()
+            .map(|result| format!("{}:{}", result.entity_type, result.entity_id))
+            .collect()
+    }
+
+    #[test]
+    fn quotes_every_word_as_a_prefix() {
+        assert_eq!(fts_query("  tom   \"sla\" "), Some("\"tom\"* \"sla\"*".to_string()));
+        assert_eq!(fts_query("NOT OR"), Some("\"NOT\"* \"OR\"*".to_string()));
+        assert_eq!(fts_query(" \" "), None);
+    }
+
+    #[test]
+    fn splits_highlighted_text() {
+        let segments = split_highlights("a \u{2}tomato\u{3} plant");
+        let parts: Vec<(&str, bool)> = segments.iter().map(|s| (s.text.as_str(), s.matched)).collect();
+        assert_eq!(parts, [("a ", false), ("tomato", true), (" plant", false)]);
+    }
+
+    #[test]
+    fn index_follows_changes() {
+        let conn = database();
+        conn.execute_batch(
+            "INSERT INTO plants (name, notes) VALUES ('Tomato', 'Needs a stake');
+             INSERT INTO activities (name, description) VALUES ('Mulch', 'Straw around the tomatoes');
+             INSERT INTO journal_entries (entry_date, text) VALUES ('2026-05-01', 'Slugs ate the lettuce');",
+        )
+        .unwrap();
+        // Names weigh more than the text below them; words match as prefixes
+        assert_eq!(found(&conn, "tomat"), ["plant:1", "activity:1"]);
+        assert_eq!(found(&conn, "slug lettuce"), ["journal:1"]);
+
+        conn.execute_batch(
+            "UPDATE plants SET name = 'Pomodoro', notes = NULL WHERE id = 1;
+             UPDATE journal_entries SET text = 'Snails' WHERE id = 1;",
+        )
+        .unwrap();
+        assert_eq!(found(&conn, "tomat"), ["activity:1"]);
+        assert_eq!(found(&conn, "pomodoro"), ["plant:1"]);
+        assert!(found(&conn, "slugs").is_empty());
+
+        conn.execute_batch("DELETE FROM plants; DELETE FROM activities; DELETE FROM journal_entries;").unwrap();
+        let rows: i64 = conn.query_row("SELECT COUNT(*) FROM search_index", [], |row| row.get(0)).unwrap();
+        assert_eq!(rows, 0);
+    }
+
+    #[test]
+    fn finds_plants_by_botanical_names_without_accents() {
+        let conn = database();
+        conn.execute(
+            "INSERT INTO plants (name, scientific_name, family) VALUES ('Crème brûlée rose', 'Rosa', 'Rosaceae')",
+            [],
+        )
+        .unwrap();
+        assert_eq!(found(&conn, "creme brulee"), ["plant:1"]);
+        assert_eq!(found(&conn, "rosaceae"), ["plant:1"]);
+    }
+}
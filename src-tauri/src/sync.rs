@@ -0,0 +1,273 @@
+use crate::backup::rows_changed;
+use crate::db::{backup_to, open_readable, run_migrations, Database};
+use chrono::{DateTime, Local};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+use tauri::{Emitter, Manager};
+
+/// How often to look for changes to the database file
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+/// How often to refresh our lease
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+/// A lease not refreshed for this long belongs to an app that quit or crashed
+const LEASE_STALE_SECONDS: i64 = 120;
+
+/// Marker written next to the database while an app has it open, so other devices sharing the
+/// file through a cloud folder can tell it is in use.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Lease {
+    pub instance_id: String,
+    pub device: String,
+    pub heartbeat: String,
+}
+
+impl Lease {
+    fn is_fresh(&self) -> bool {
+        DateTime::parse_from_rfc3339(&self.heartbeat)
+            .map(|heartbeat| Local::now().signed_duration_since(heartbeat).num_seconds() < LEASE_STALE_SECONDS)
+            .unwrap_or(false)
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct SyncStatus {
+    /// The database file was changed by something other than this app
+    pub changed_externally: bool,
+    /// Another app instance that currently has the database open
+    pub other_device: Option<Lease>,
+}
+
+pub struct SyncState {
+    pub instance_id: String,
+    pub device: String,
+    status: Mutex<SyncStatus>,
+    /// Set when the app replaced the database itself, so the watcher starts over
+    rebaseline: AtomicBool,
+}
+
+impl SyncState {
+    pub fn new() -> Self {
+        Self {
+            instance_id: format!("{}-{}", std::process::id(), Local::now().timestamp_millis()),
+            device: device_name(),
+            status: Mutex::new(SyncStatus::default()),
+            rebaseline: AtomicBool::new(false),
+        }
+    }
+
+    pub fn status(&self) -> SyncStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    /// Tell the watcher the database was swapped by the app, e.g. after a restore or relocation.
+    pub fn database_replaced(&self, app: &tauri::AppHandle) {
+        self.rebaseline.store(true, Ordering::SeqCst);
+        self.update(app, |status| status.changed_externally = false);
+    }
+
+    fn update(&self, app: &tauri::AppHandle, change: impl FnOnce(&mut SyncStatus)) {
+        let mut status = self.status.lock().unwrap();
+        let before = status.clone();
+        change(&mut status);
+        if *status != before {
+            let _ = app.emit("sync-status", status.clone());
+        }
+    }
+}
+
+fn device_name() -> String {
+    std::process::Command::new("hostname")
+        .output()
+        .ok()
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "Unknown device".to_string())
+}
+
+pub fn lease_path(db_path: &Path) -> PathBuf {
+    db_path.with_extension("db.lock")
+}
+
+fn read_lease(path: &Path) -> Option<Lease> {
+    let content = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Write our lease, returning the lease of another instance if one is still active.
+fn refresh_lease(path: &Path, instance_id: &str, device: &str) -> Option<Lease> {
+    let other = read_lease(path).filter(|lease| lease.instance_id != instance_id && lease.is_fresh());
+
+    let lease = Lease {
+        instance_id: instance_id.to_string(),
+        device: device.to_string(),
+        heartbeat: Local::now().to_rfc3339(),
+    };
+    if let Err(e) = std::fs::write(path, serde_json::to_string_pretty(&lease).unwrap()) {
+        eprintln!("Failed to write database lease: {}", e);
+    }
+    other
+}
+
+/// Remove the lease if it is still ours.
+pub fn release_lease(path: &Path, instance_id: &str) {
+    if read_lease(path).is_some_and(|lease| lease.instance_id == instance_id) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// What the database looked like at one check.
+struct Observation {
+    modified: Option<SystemTime>,
+    len: u64,
+    data_version: i64,
+    rows_changed: u64,
+}
+
+impl Observation {
+    fn take(conn: &Connection, db_path: &Path) -> Self {
+        let metadata = std::fs::metadata(db_path).ok();
+        Self {
+            modified: metadata.as_ref().and_then(|m| m.modified().ok()),
+            len: metadata.map(|m| m.len()).unwrap_or(0),
+            data_version: conn.query_row("PRAGMA data_version", [], |row| row.get(0)).unwrap_or(0),
+            rows_changed: rows_changed(conn),
+        }
+    }
+
+    fn changed_externally_since(&self, previous: &Observation) -> bool {
+        // data_version moves when another connection commits to the file we have open
+        if self.data_version != previous.data_version {
+            return true;
+        }
+        // A sync client usually replaces the file instead, which only shows in its metadata.
+        // That also changes when we write, so it only counts if we wrote nothing in between.
+        let file_changed = self.modified != previous.modified || self.len != previous.len;
+        file_changed && self.rows_changed == previous.rows_changed
+    }
+}
+
+/// Keep a lease on the database while the app runs, and watch for the file being changed by
+/// another device or process. Findings are published as `sync-status` events.
+pub fn start_sync_watcher(app: tauri::AppHandle) {
+    std::thread::spawn(move || {
+        let mut current_lease: Option<PathBuf> = None;
+        let mut last_heartbeat: Option<Instant> = None;
+        let mut previous: Option<Observation> = None;
+
+        loop {
+            let sync = app.state::<SyncState>();
+            let db = app.state::<Database>();
+            let db_path = db.get_path();
+
+            if sync.rebaseline.swap(false, Ordering::SeqCst) {
+                previous = None;
+            }
+
+            // Follow the database to its new location after a relocation
+            let lease = lease_path(&db_path);
+            if current_lease.as_ref() != Some(&lease) {
+                if let Some(old) = current_lease.take() {
+                    release_lease(&old, &sync.instance_id);
+                }
+                current_lease = Some(lease.clone());
+                last_heartbeat = None;
+                previous = None;
+            }
+
+            if last_heartbeat.is_none_or(|at| at.elapsed() >= HEARTBEAT_INTERVAL) {
+                let other = refresh_lease(&lease, &sync.instance_id, &sync.device);
+                sync.update(&app, |status| status.other_device = other);
+                last_heartbeat = Some(Instant::now());
+            }
+
+            if let Ok(conn) = db.conn.lock() {
+                let observation = Observation::take(&conn, &db_path);
+                if previous.as_ref().is_some_and(|p| observation.changed_externally_since(p)) {
+                    sync.update(&app, |status| status.changed_externally = true);
+                }
+                previous = Some(observation);
+            }
+
+            std::thread::sleep(CHECK_INTERVAL);
+        }
+    });
+}
+
+/// How to continue after the database file changed underneath us.
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ExternalChangeResolution {
+    /// Reopen the file as it is on disk now, dropping our view of it
+    Reload,
+    /// Save our view as a conflict copy, then reopen the file on disk
+    ConflictCopy,
+    /// Save the file on disk as a conflict copy, then overwrite it with our view
+    KeepMine,
+}
+
+/// A file next to the database like `tuinapp (conflict MacBook 2026-05-01 10-15-00).db`.
+fn conflict_copy_path(db_path: &Path, label: &str) -> PathBuf {
+    let stem = db_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let label: String = label
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == ' ' { c } else { '-' })
+        .collect();
+    let timestamp = Local::now().format("%Y-%m-%d %H-%M-%S");
+    db_path.with_file_name(format!("{} (conflict {} {}).db", stem, label, timestamp))
+}
+
+/// Write what `conn` sees to `destination` via a temporary file, replacing it atomically.
+fn write_snapshot(conn: &Connection, destination: &Path, key: Option<&str>) -> Result<(), String> {
+    let partial_path = destination.with_extension("db.partial");
+    let result = backup_to(conn, &partial_path, key)
+        .map_err(|e| e.to_string())
+        .and_then(|_| std::fs::rename(&partial_path, destination).map_err(|e| e.to_string()));
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&partial_path);
+        return Err(format!("Failed to write {}: {}", destination.display(), e));
+    }
+    Ok(())
+}
+
+/// Resolve an external change and reopen the database. Returns the conflict copy, if one was
+/// made. Note that our connection only still sees our own version when the file was replaced
+/// rather than modified in place, which is how sync clients normally update it.
+pub fn resolve_external_change(
+    app: &tauri::AppHandle,
+    db: &Database,
+    resolution: ExternalChangeResolution,
+) -> Result<Option<String>, String> {
+    let sync = app.state::<SyncState>();
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let db_path = db.get_path();
+    let key = db.get_key();
+
+    let conflict_copy = match resolution {
+        ExternalChangeResolution::Reload => None,
+        ExternalChangeResolution::ConflictCopy => {
+            let copy_path = conflict_copy_path(&db_path, &sync.device);
+            write_snapshot(&conn, &copy_path, key.as_deref())?;
+            Some(copy_path)
+        }
+        ExternalChangeResolution::KeepMine => {
+            let copy_path = conflict_copy_path(&db_path, "other device");
+            std::fs::copy(&db_path, &copy_path).map_err(|e| format!("Failed to save conflict copy: {}", e))?;
+            write_snapshot(&conn, &db_path, key.as_deref())?;
+            Some(copy_path)
+        }
+    };
+
+    // The other device may have encrypted or decrypted the file
+    let (mut reopened, key) = open_readable(&db_path, key.as_deref())?;
+    run_migrations(&mut reopened)?;
+    *conn = reopened;
+    *db.key.lock().unwrap() = key;
+
+    sync.database_replaced(app);
+    Ok(conflict_copy.map(|path| path.display().to_string()))
+}
@@ -1,6 +1,5 @@
 import { invoke } from '@tauri-apps/api/core';
-import { copyFile } from '@tauri-apps/plugin-fs';
-import type { Plant, Activity, PlantPhoto } from './types';
+import type { Plant, ResolvedPlant, PlantTask, DuePlantTask, BloomCalendar, PlantCategory, Tag, PlantFilter, PlantPage, SmartList, Activity, JournalEntry, JournalFilter, TagCount, SearchResult, PlantPhoto, PhotoImage, PhotoImportReport, PhotoOwner, PhotoSettings, Location, Supplier, SupplierGroup, IntegrityReport, BackupInfo, BackupRetention, BackupSchedule, EncryptionStatus, SyncStatus, ExternalChangeResolution, MergeReport, ProfileInfo, ProfileList } from './types';
 
 // Plants
 export const getAllPlants = () => invoke<Plant[]>('get_all_plants');
@@ -8,6 +7,42 @@ export const createPlant = (plant: Plant) => invoke<Plant>('create_plant', { pla
 export const updatePlant = (plant: Plant) => invoke<void>('update_plant', { plant });
 export const deletePlant = (id: number) => invoke<void>('delete_plant', { id });
 export const getPlantsToReorder = () => invoke<Plant[]>('get_plants_to_reorder');
+export const queryPlants = (filter: PlantFilter) => invoke<PlantPage>('query_plants', { filter });
+export const resolvePlant = (id: number) => invoke<ResolvedPlant>('resolve_plant', { id });
+
+// Plant tasks
+export const getPlantTasks = (plantId: number) => invoke<PlantTask[]>('get_plant_tasks', { plantId });
+export const createPlantTask = (task: PlantTask) => invoke<PlantTask>('create_plant_task', { task });
+export const updatePlantTask = (task: PlantTask) => invoke<void>('update_plant_task', { task });
+// Marks the task done in the given year, this year when left out
+export const completePlantTask = (id: number, year?: number) => invoke<void>('complete_plant_task', { id, year });
+export const deletePlantTask = (id: number) => invoke<void>('delete_plant_task', { id });
+
+// Plant categories
+export const getPlantCategories = () => invoke<PlantCategory[]>('get_plant_categories');
+export const createPlantCategory = (category: PlantCategory) =>
+  invoke<PlantCategory>('create_plant_category', { category });
+export const updatePlantCategory = (category: PlantCategory) => invoke<void>('update_plant_category', { category });
+export const reorderPlantCategories = (categoryIds: number[]) =>
+  invoke<void>('reorder_plant_categories', { categoryIds });
+export const deletePlantCategory = (id: number) => invoke<void>('delete_plant_category', { id });
+
+// Tags
+export const getTags = () => invoke<Tag[]>('get_tags');
+export const createTag = (tag: Tag) => invoke<Tag>('create_tag', { tag });
+export const updateTag = (tag: Tag) => invoke<void>('update_tag', { tag });
+export const deleteTag = (id: number) => invoke<void>('delete_tag', { id });
+export const setPlantTags = (plantId: number, tagIds: number[]) =>
+  invoke<void>('set_plant_tags', { plantId, tagIds });
+export const setActivityTags = (activityId: number, tagIds: number[]) =>
+  invoke<void>('set_activity_tags', { activityId, tagIds });
+
+// Smart lists
+export const getSmartLists = () => invoke<SmartList[]>('get_smart_lists');
+export const createSmartList = (smartList: SmartList) => invoke<SmartList>('create_smart_list', { smartList });
+export const runSmartList = (id: number, limit?: number, offset?: number) =>
+  invoke<PlantPage>('run_smart_list', { id, limit, offset });
+export const deleteSmartList = (id: number) => invoke<void>('delete_smart_list', { id });
 
 // Activities
 export const getAllActivities = () => invoke<Activity[]>('get_all_activities');
@@ -15,6 +50,18 @@ export const createActivity = (activity: Activity) => invoke<Activity>('create_a
 export const updateActivity = (activity: Activity) => invoke<void>('update_activity', { activity });
 export const deleteActivity = (id: number) => invoke<void>('delete_activity', { id });
 
+// Suppliers
+export const getAllSuppliers = () => invoke<Supplier[]>('get_all_suppliers');
+export const createSupplier = (supplier: Supplier) => invoke<Supplier>('create_supplier', { supplier });
+export const updateSupplier = (supplier: Supplier) => invoke<void>('update_supplier', { supplier });
+export const deleteSupplier = (id: number) => invoke<void>('delete_supplier', { id });
+
+// Shopping list
+export const getShoppingList = () => invoke<SupplierGroup[]>('get_shopping_list');
+export const getShoppingListText = () => invoke<string>('get_shopping_list_text');
+export const exportShoppingListCsv = (path: string) => invoke<void>('export_shopping_list_csv', { path });
+export const generateShoppingListPdf = () => invoke<string>('generate_shopping_list_pdf');
+
 // Calendar
 export interface MonthData {
   sow_early: Plant[];
@@ -22,30 +69,114 @@ export interface MonthData {
   plant_early: Plant[];
   plant_late: Plant[];
   activities: Activity[];
+  last_year_journal: JournalEntry[];
+  categories: PlantCategory[];
+  plant_tasks: DuePlantTask[];
 }
 
-export const getMonthData = (month: number) => invoke<MonthData>('get_month_data', { month });
+// Only plants and activities with one of the tags are included when tags are given
+export const getMonthData = (month: number, tagIds?: number[]) =>
+  invoke<MonthData>('get_month_data', { month, tagIds });
+export const getBloomCalendar = (tagIds?: number[]) => invoke<BloomCalendar>('get_bloom_calendar', { tagIds });
+
+// Journal
+export const getJournalEntries = (filter?: JournalFilter) => invoke<JournalEntry[]>('get_journal_entries', { filter });
+export const getJournalTags = () => invoke<TagCount[]>('get_journal_tags');
+export const createJournalEntry = (entry: JournalEntry) => invoke<JournalEntry>('create_journal_entry', { entry });
+export const updateJournalEntry = (entry: JournalEntry) => invoke<void>('update_journal_entry', { entry });
+export const deleteJournalEntry = (id: number) => invoke<void>('delete_journal_entry', { id });
+
+// Search
+export const search = (query: string, limit?: number) => invoke<SearchResult[]>('search', { query, limit });
 
-// Photos
-export const getPhotos = (plantId: number) => invoke<PlantPhoto[]>('get_photos', { plantId });
-export const addPhoto = (plantId: number, imageData: string, sortOrder: number) =>
-  invoke<PlantPhoto>('add_photo', { plantId, imageData, sortOrder });
+// Photos, of a plant, activity, journal entry or plant task
+export const getPhotos = (owner: PhotoOwner) => invoke<PlantPhoto[]>('get_photos', { owner });
+// Ordered by the date the photos were taken
+export const getPhotoTimeline = (owner: PhotoOwner) => invoke<PlantPhoto[]>('get_photo_timeline', { owner });
+// Photos from around the same date last year, closest first
+export const getPhotosAYearAgo = (owner: PhotoOwner, date?: string) =>
+  invoke<PlantPhoto[]>('get_photos_a_year_ago', { owner, date });
+export const addPhoto = (owner: PhotoOwner, imageData: string, sortOrder: number) =>
+  invoke<PlantPhoto>('add_photo', { owner, imageData, sortOrder });
+// Add image files from disk, skipping duplicates of the owner's photos
+export const importPhotos = (owner: PhotoOwner, paths: string[]) =>
+  invoke<PhotoImportReport>('import_photos', { owner, paths });
 export const deletePhoto = (id: number) => invoke<void>('delete_photo', { id });
+export const getPhotoImage = (id: number, original = false) => invoke<PhotoImage>('get_photo_image', { id, original });
+export const photoDataUrl = (image: PhotoImage) => `data:${image.mime_type};base64,${image.data}`;
+export const reorderPhotos = (owner: PhotoOwner, photoIds: number[]) =>
+  invoke<void>('reorder_photos', { owner, photoIds });
+export const updatePhoto = (id: number, caption: string | null, takenAt: string | null) =>
+  invoke<void>('update_photo', { id, caption, takenAt });
+// Pass null as id to clear the plant's primary photo
+export const setPrimaryPhoto = (owner: PhotoOwner, id: number | null) =>
+  invoke<void>('set_primary_photo', { owner, id });
+export const getPhotoSettings = () => invoke<PhotoSettings>('get_photo_settings');
+export const savePhotoSettings = (settings: PhotoSettings) => invoke<void>('save_photo_settings', { settings });
+export const getLocation = () => invoke<Location>('get_location');
+// Returns how many half-months plant periods are shifted for this location
+export const saveLocation = (location: Location) => invoke<number>('save_location', { location });
+
+// Printing
+export const generatePlantSheetPdf = (plantIds: number[], maxPhotos?: number) =>
+  invoke<string>('generate_plant_sheet_pdf', { plantIds, maxPhotos });
 
 // Import
-export const importPlantsTsv = (tsvContent: string) =>
-  invoke<number>('import_plants_tsv', { tsvContent });
+export const importPlantsTsv = (tsvContent: string, splitVarieties = false) =>
+  invoke<number>('import_plants_tsv', { tsvContent, splitVarieties });
+export const linkVarietiesByName = () => invoke<number>('link_varieties_by_name');
 
 // Database
 export const getDatabasePath = () => invoke<string>('get_database_path');
+export const integrityCheck = () => invoke<IntegrityReport>('integrity_check');
+
+// Backups
+export const listBackups = () => invoke<BackupInfo[]>('list_backups');
+export const backupNow = () => invoke<string>('backup_now');
+export const restoreBackup = (fileName: string) => invoke<void>('restore_backup', { fileName });
+export const getBackupRetention = () => invoke<BackupRetention>('get_backup_retention');
+export const saveBackupRetention = (retention: BackupRetention) =>
+  invoke<number>('save_backup_retention', { retention });
+export const getBackupSchedule = () => invoke<BackupSchedule>('get_backup_schedule');
+export const saveBackupSchedule = (schedule: BackupSchedule) =>
+  invoke<void>('save_backup_schedule', { schedule });
+
+// Schema
+export interface SchemaInfo {
+  current_version: number;
+  latest_version: number;
+}
+
+export const getStartupError = () => invoke<string | null>('get_startup_error');
+export const retryMigrations = () => invoke<void>('retry_migrations');
+export const getSchemaInfo = () => invoke<SchemaInfo>('get_schema_info');
+export const migrateTo = (version: number) => invoke<void>('migrate_to', { version });
+
+// Move the current database to newPath, or switch to the database already at newPath
+export const relocateDatabase = (newPath: string, mode: 'move' | 'open') =>
+  invoke<string>('relocate_database', { newPath, mode });
+
+export const getRecentDatabases = () => invoke<string[]>('get_recent_databases');
+
+// Gardens (profiles), each with its own database and settings
+export const listProfiles = () => invoke<ProfileList>('list_profiles');
+export const createProfile = (name: string, databasePath?: string) =>
+  invoke<ProfileInfo>('create_profile', { name, databasePath });
+export const switchProfile = (id: string) => invoke<void>('switch_profile', { id });
+export const deleteProfile = (id: string) => invoke<void>('delete_profile', { id });
 
-export const moveDatabase = async (newPath: string): Promise<string> => {
-  // Get current database path
-  const currentPath = await getDatabasePath();
+// Merge another copy of the database, e.g. a cloud "conflicted copy", into this one
+export const mergeDatabase = (otherPath: string) => invoke<MergeReport>('merge_database', { otherPath });
 
-  // Copy using Tauri's fs plugin (respects sandbox permissions from dialog)
-  await copyFile(currentPath, newPath);
+// Encryption at rest (only available in builds with the encryption feature)
+export const getEncryptionStatus = () => invoke<EncryptionStatus>('get_encryption_status');
+export const isDatabaseLocked = () => invoke<boolean>('is_database_locked');
+export const unlockDatabase = (passphrase: string) => invoke<void>('unlock_database', { passphrase });
+export const encryptDatabase = (passphrase: string) => invoke<void>('encrypt_database', { passphrase });
+export const decryptDatabase = () => invoke<void>('decrypt_database');
+export const changePassphrase = (passphrase: string) => invoke<void>('change_passphrase', { passphrase });
 
-  // Save the new path to config
-  return invoke<string>('save_database_path', { newPath });
-};
+// Sync
+export const getSyncStatus = () => invoke<SyncStatus>('get_sync_status');
+export const resolveExternalChange = (resolution: ExternalChangeResolution) =>
+  invoke<string | null>('resolve_external_change', { resolution });
@@ -1,12 +1,138 @@
 export interface Plant {
   id?: number;
   name: string;
-  plant_type?: 'vegetable_fruit' | 'flower' | 'herb';
+  // Key of the plant's category
+  plant_type?: string | null;
   sun_requirements: number;
   sow_periods: number;
   plant_periods: number;
   notes?: string;
   needs_reorder: boolean;
+  supplier_id?: number | null;
+  packets_needed: number;
+  last_price?: number | null;
+  created_at?: string;
+  updated_at?: string;
+  scientific_name?: string | null;
+  cultivar?: string | null;
+  family?: string | null;
+  lifecycle?: Lifecycle | null;
+  hardiness_zone?: string | null;
+  // Depths, spacings and sizes in centimetres
+  sowing_depth_cm?: number | null;
+  in_row_spacing_cm?: number | null;
+  row_spacing_cm?: number | null;
+  mature_height_cm?: number | null;
+  mature_spread_cm?: number | null;
+  water_needs?: WaterNeeds | null;
+  // The species this plant is a variety of
+  parent_id?: number | null;
+  // Half-month periods in which the plant flowers
+  bloom_periods: number;
+  flower_color?: string | null;
+  // Year the plant went into the garden; recurring tasks count from here
+  established_year?: number | null;
+  // Ids of the plant's tags; saving without them leaves the tags unchanged
+  tag_ids?: number[];
+  // Fields a variety takes from its species and keeps following
+  inherited_fields?: InheritedField[];
+}
+
+// Values a variety shares with its species unless it sets its own
+export const INHERITED_FIELDS = [
+  'plant_type',
+  'sun_requirements',
+  'sow_periods',
+  'plant_periods',
+  'scientific_name',
+  'family',
+  'lifecycle',
+  'hardiness_zone',
+  'sowing_depth_cm',
+  'in_row_spacing_cm',
+  'row_spacing_cm',
+  'mature_height_cm',
+  'mature_spread_cm',
+  'water_needs',
+  'bloom_periods',
+] as const;
+
+export type InheritedField = (typeof INHERITED_FIELDS)[number];
+
+export interface ResolvedPlant {
+  plant: Plant;
+  species?: Plant | null;
+  varieties: Plant[];
+}
+
+export type Lifecycle = 'annual' | 'biennial' | 'perennial';
+
+export type WaterNeeds = 'low' | 'medium' | 'high';
+
+export type BitmaskMatch = 'any' | 'all';
+
+export type PlantSortKey =
+  | 'name'
+  | 'plant_type'
+  | 'first_sow_period'
+  | 'first_plant_period'
+  | 'last_price'
+  | 'created_at'
+  | 'updated_at';
+
+export interface PlantSort {
+  key: PlantSortKey;
+  descending?: boolean;
+}
+
+// Every field is optional; unset fields match all plants
+export interface PlantFilter {
+  plant_types?: string[];
+  sun_requirements?: number;
+  sun_match?: BitmaskMatch;
+  sow_periods?: number;
+  plant_periods?: number;
+  needs_reorder?: boolean | null;
+  tag_ids?: number[];
+  tag_match?: BitmaskMatch;
+  text?: string;
+  created_from?: string;
+  created_to?: string;
+  updated_from?: string;
+  updated_to?: string;
+  sort?: PlantSort[];
+  limit?: number;
+  offset?: number;
+}
+
+export interface PlantPage {
+  plants: Plant[];
+  total: number;
+}
+
+export interface SmartList {
+  id?: number;
+  name: string;
+  filter: PlantFilter;
+  created_at?: string;
+  updated_at?: string;
+}
+
+export interface PlantCategory {
+  id?: number;
+  key: string;
+  name: string;
+  color: string;
+  icon?: string | null;
+  sort_order: number;
+  created_at?: string;
+  updated_at?: string;
+}
+
+export interface Tag {
+  id?: number;
+  name: string;
+  color: string;
   created_at?: string;
   updated_at?: string;
 }
@@ -18,14 +144,87 @@ export const SUN_BITS = {
   FULL_SHADE: 4,
 } as const;
 
+// What a photo belongs to
+export type PhotoOwner = { type: 'plant' | 'activity' | 'journal_entry' | 'plant_task'; id: number };
+
 export interface PlantPhoto {
   id?: number;
-  plant_id: number;
+  plant_id?: number | null;
+  activity_id?: number | null;
+  journal_entry_id?: number | null;
+  plant_task_id?: number | null;
   sort_order: number;
-  image_data?: string;
+  thumbnail?: string;
+  has_original: boolean;
+  caption?: string | null;
+  taken_at?: string | null;
+  is_primary: boolean;
   created_at?: string;
 }
 
+export interface PhotoImage {
+  mime_type: string;
+  data: string;
+}
+
+export interface PhotoImportReport {
+  imported: PlantPhoto[];
+  duplicates: string[];
+  failed: string[];
+}
+
+export interface PhotoSettings {
+  keep_originals: boolean;
+}
+
+export type Hemisphere = 'north' | 'south';
+
+// Where the garden is. Plant periods are entered for a Dutch climate and shifted to match.
+export interface Location {
+  latitude?: number | null;
+  hemisphere: Hemisphere;
+  last_frost?: string | null; // MM-DD
+  first_frost?: string | null; // MM-DD
+  // Extra half-months on top of the frost dates
+  adjust_periods: number;
+}
+
+// Recurring care, due in its periods once interval_years have passed since it was last done
+export interface PlantTask {
+  id?: number;
+  plant_id: number;
+  name: string;
+  periods: number;
+  interval_years: number;
+  last_done_year?: number | null;
+  created_at?: string;
+  updated_at?: string;
+}
+
+export interface DuePlantTask {
+  task: PlantTask;
+  plant_name: string;
+}
+
+export interface BloomingPlant {
+  id: number;
+  name: string;
+  flower_color?: string | null;
+}
+
+export interface BloomPeriod {
+  // Period bit, 0 for early January through 23 for late December
+  period: number;
+  label: string;
+  plants: BloomingPlant[];
+}
+
+export interface BloomCalendar {
+  periods: BloomPeriod[];
+  // Periods in which nothing flowers
+  gaps: number[];
+}
+
 export interface Activity {
   id?: number;
   name: string;
@@ -33,6 +232,155 @@ export interface Activity {
   active_periods: number;
   created_at?: string;
   updated_at?: string;
+  // Ids of the activity's tags; saving without them leaves the tags unchanged
+  tag_ids?: number[];
+}
+
+export interface JournalEntry {
+  id?: number;
+  entry_date: string; // YYYY-MM-DD
+  text: string;
+  tags: string[];
+  plant_id?: number | null;
+  activity_id?: number | null;
+  created_at?: string;
+  updated_at?: string;
+}
+
+export interface JournalFilter {
+  text?: string;
+  tag?: string;
+  plant_id?: number;
+  activity_id?: number;
+  from?: string;
+  to?: string;
+}
+
+export interface TagCount {
+  tag: string;
+  count: number;
+}
+
+export interface TextSegment {
+  text: string;
+  matched: boolean;
+}
+
+export type SearchEntityType = 'plant' | 'activity' | 'journal';
+
+export interface SearchResult {
+  entity_type: SearchEntityType;
+  entity_id: number;
+  title: TextSegment[];
+  snippet: TextSegment[];
+  rank: number;
+}
+
+export interface Supplier {
+  id?: number;
+  name: string;
+  website?: string;
+  email?: string;
+  notes?: string;
+  created_at?: string;
+  updated_at?: string;
+}
+
+export interface ShoppingListItem {
+  plant_id: number;
+  name: string;
+  packets_needed: number;
+  last_price?: number | null;
+  sow_by?: string | null;
+  periods_until_sowing?: number | null;
+}
+
+export interface SupplierGroup {
+  supplier_id?: number | null;
+  supplier_name: string;
+  items: ShoppingListItem[];
+  estimated_total: number;
+}
+
+export interface IntegrityReport {
+  ok: boolean;
+  integrity_messages: string[];
+  orphaned_photos_removed: number;
+  dangling_suppliers_cleared: number;
+  foreign_key_violations: string[];
+  bytes_reclaimed: number;
+}
+
+export interface BackupInfo {
+  file_name: string;
+  size_bytes: number;
+  created_at: string;
+  compressed: boolean;
+  plant_count: number | null;
+  schema_version: number | null;
+}
+
+export interface BackupRetention {
+  keep_last: number;
+  keep_daily: number;
+  keep_weekly: number;
+  keep_monthly: number;
+}
+
+export interface ProfileInfo {
+  id: string;
+  name: string;
+  database_path: string;
+}
+
+export interface ProfileList {
+  active: string;
+  profiles: ProfileInfo[];
+}
+
+export interface TableMergeSummary {
+  table: string;
+  added: number;
+  updated: number;
+  deleted: number;
+  skipped: number;
+}
+
+export interface MergeConflict {
+  table: string;
+  label: string;
+  kept: 'this' | 'other';
+  this_updated_at: string | null;
+  other_updated_at: string | null;
+}
+
+export interface MergeReport {
+  tables: TableMergeSummary[];
+  conflicts: MergeConflict[];
+}
+
+export interface Lease {
+  instance_id: string;
+  device: string;
+  heartbeat: string;
+}
+
+export interface SyncStatus {
+  changed_externally: boolean;
+  other_device: Lease | null;
+}
+
+export interface EncryptionStatus {
+  available: boolean;
+  encrypted: boolean;
+}
+
+export type ExternalChangeResolution = 'reload' | 'conflict_copy' | 'keep_mine';
+
+export interface BackupSchedule {
+  interval_minutes: number;
+  after_changes: number;
+  compress: boolean;
 }
 
 export const MONTHS = [
@@ -40,11 +388,27 @@ export const MONTHS = [
   'July', 'August', 'September', 'October', 'November', 'December'
 ] as const;
 
-export const PLANT_TYPES = [
-  { value: 'vegetable_fruit', label: 'Vegetable/Fruit', icon: '🥕' },
-  { value: 'flower', label: 'Flower', icon: '🌸' },
-  { value: 'herb', label: 'Herb', icon: '🌿' },
-] as const;
+// Icon of the category with `key`, '-' for plants without a category
+export const categoryIcon = (categories: PlantCategory[], key?: string | null): string => {
+  if (!key) return '-';
+  const found = categories.find(c => c.key === key);
+  return found ? found.icon || '●' : '-';
+};
+
+export const categoryName = (categories: PlantCategory[], key?: string | null): string =>
+  categories.find(c => c.key === key)?.name ?? '';
+
+export const LIFECYCLES: { value: Lifecycle; label: string }[] = [
+  { value: 'annual', label: 'Annual' },
+  { value: 'biennial', label: 'Biennial' },
+  { value: 'perennial', label: 'Perennial' },
+];
+
+export const WATER_NEEDS: { value: WaterNeeds; label: string }[] = [
+  { value: 'low', label: 'Low' },
+  { value: 'medium', label: 'Medium' },
+  { value: 'high', label: 'High' },
+];
 
 export const SUN_REQUIREMENTS = [
   { value: 'full_sun', label: 'Full Sun', icon: '☀️' },